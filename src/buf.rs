@@ -0,0 +1,120 @@
+use bytes::{ Buf, BufMut };
+use bytes::buf::UninitSlice;
+
+use allocator_api2::alloc::Allocator;
+
+use Buffer;
+
+/// `bytes::Buf` over a buffer's bytes, padding included, advancing an internal cursor
+pub struct BufferBuf<'b> {
+    data: &'b [u8],
+    position: usize,
+}
+
+impl <'b> BufferBuf<'b> {
+    pub fn new<'a, T: 'a, A: Allocator>(buffer: &'b Buffer<'a, T, A>) -> Self {
+        BufferBuf {
+            data: buffer.data(),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl <'b> Buf for BufferBuf<'b> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn chunk(&self) -> &[u8] {
+        &self.data[self.position..]
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(cnt <= self.remaining(), "cannot advance past the end of the buffer: {} > {}", cnt, self.remaining());
+        self.position += cnt;
+    }
+}
+
+/// `bytes::BufMut` over a buffer's bytes, filling the allocation from offset 0.
+/// The unfilled tail is exposed through `chunk_mut` and never grows past the allocation.
+pub struct BufferBufMut<'b> {
+    data: &'b mut [u8],
+    filled: usize,
+}
+
+impl <'b> BufferBufMut<'b> {
+    pub fn new<'a, T: 'a, A: Allocator>(buffer: &'b mut Buffer<'a, T, A>) -> Self {
+        BufferBufMut {
+            data: buffer.data_mut(),
+            filled: 0,
+        }
+    }
+
+    /// Bytes written so far
+    pub fn filled(&self) -> &[u8] {
+        &self.data[..self.filled]
+    }
+}
+
+unsafe impl <'b> BufMut for BufferBufMut<'b> {
+    fn remaining_mut(&self) -> usize {
+        self.data.len() - self.filled
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        assert!(cnt <= self.remaining_mut(), "cannot advance past the end of the buffer: {} > {}", cnt, self.remaining_mut());
+        self.filled += cnt;
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::new(&mut self.data[self.filled..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_bytes_through_buf_adapters() {
+        let mut buf = Buffer::<u8>::new(8).unwrap();
+        {
+            let mut sink = buf.buf_mut();
+            sink.put_u16(0x0102);
+            sink.put_slice(&[3, 4, 5]);
+            sink.put_u8(6);
+            assert_eq!(sink.filled(), &[1, 2, 3, 4, 5, 6]);
+            assert_eq!(sink.remaining_mut(), 2);
+        }
+        let mut source = buf.buf();
+        assert_eq!(source.get_u16(), 0x0102);
+        let mut rest = [0u8; 4];
+        source.copy_to_slice(&mut rest);
+        assert_eq!(rest, [3, 4, 5, 6]);
+        assert_eq!(source.remaining(), 2);
+        assert_eq!(source.position(), 6);
+    }
+
+    #[test]
+    fn should_stop_buf_mut_at_capacity() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        let mut sink = buf.buf_mut();
+        sink.put_slice(&[1, 2]);
+        assert!(!sink.has_remaining_mut());
+        assert_eq!(sink.chunk_mut().len(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_advancing_past_capacity() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        let mut sink = buf.buf_mut();
+        unsafe {
+            sink.advance_mut(3);
+        }
+    }
+}
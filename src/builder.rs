@@ -0,0 +1,179 @@
+use core::cmp::max;
+use core::marker::PhantomData;
+use core::mem;
+
+use allocator_api2::alloc::Global;
+
+use { base_align, buffer_aligned, empty, plan, Buffer, Error, Padding };
+
+/// One place to combine the options the `Buffer` constructors each cover on their own.
+///
+/// Starts from an empty, packed buffer. `align` is applied on top of `padding`, rounding the
+/// stride up to a multiple of it, as `Buffer::aligned` does.
+pub struct BufferBuilder<T> {
+    cap: usize,
+    padding: Padding,
+    align: Option<usize>,
+    #[cfg(feature = "std")]
+    locked: bool,
+    huge_pages: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl <T> BufferBuilder<T> {
+    pub fn new() -> Self {
+        BufferBuilder {
+            cap: 0,
+            padding: Padding::None,
+            align: None,
+            #[cfg(feature = "std")]
+            locked: false,
+            huge_pages: false,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn capacity(mut self, cap: usize) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Base pointer alignment, a power of two no smaller than `T`'s alignment
+    pub fn align(mut self, align: usize) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    /// Locks the allocation into RAM, `build` fails with `LockFailed` when it can't be
+    #[cfg(feature = "std")]
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Backs the buffer with 2 MiB huge pages where the platform has them, falling back to an
+    /// equally aligned heap allocation otherwise
+    pub fn huge_pages(mut self, huge_pages: bool) -> Self {
+        self.huge_pages = huge_pages;
+        self
+    }
+
+    pub fn build<'a>(self) -> Result<Buffer<'a, T>, Error> where T: 'a {
+        let (mut padding, mut align) = (self.padding, base_align::<T>(self.padding));
+        let (_, stride, _) = plan::<T>(self.cap, padding)?;
+        if let Some(requested) = self.align {
+            if !requested.is_power_of_two() || requested < mem::align_of::<T>() {
+                return Err(Error::InvalidAlignment);
+            }
+            let rounded = stride.checked_add(requested - 1)
+                .ok_or(Error::BufferSizeOverflow)? / requested * requested;
+            if rounded != stride {
+                padding = Padding::Padded(rounded);
+            }
+            align = max(align, requested);
+        }
+        let (size, padded_size, alloc_size) = plan::<T>(self.cap, padding)?;
+        let mut buffer = if self.cap == 0 {
+            empty(size, padded_size, Global)
+        } else if self.huge_pages {
+            huge_pages(self.cap, size, padded_size, alloc_size, align)?
+        } else {
+            buffer_aligned(self.cap, size, padded_size, alloc_size, align)?
+        };
+        buffer.padding = padding;
+        #[cfg(feature = "std")]
+        {
+            if self.locked {
+                buffer.lock()?;
+                buffer.locked = true;
+            }
+        }
+        Ok(buffer)
+    }
+}
+
+impl <T> Default for BufferBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn huge_pages<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize) -> Result<Buffer<'a, T>, Error> {
+    ::huge_page_from(cap, size, padded_size, alloc_size, max(align, ::HUGE_PAGE_SIZE))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn huge_pages<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize) -> Result<Buffer<'a, T>, Error> {
+    log::warn!("huge pages unavailable, falling back to heap allocation of {} bytes", alloc_size);
+    buffer_aligned(cap, size, padded_size, alloc_size, max(align, 2 * 1024 * 1024))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use cache_line_size;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_build_cache_aligned_locked_buffer() {
+        let built = BufferBuilder::<u32>::new()
+            .capacity(4)
+            .padding(Padding::CacheAligned)
+            .locked(true)
+            .build();
+        let buf = match built {
+            Ok(buf) => buf,
+            Err(Error::LockFailed) => return, // e.g. RLIMIT_MEMLOCK exceeded
+            Err(error) => panic!("unexpected error: {:?}", error),
+        };
+        assert!(buf.locked);
+        assert!(buf.is_cache_aligned());
+        assert_eq!((buf.cap(), buf.padded_size()), (4, cache_line_size()));
+        assert_eq!(buf.padding_mode(), Padding::CacheAligned);
+    }
+
+    #[test]
+    fn should_round_padded_stride_up_to_alignment() {
+        let mut buf = BufferBuilder::<u16>::new()
+            .capacity(3)
+            .padding(Padding::Padded(6))
+            .align(16)
+            .build()
+            .unwrap();
+        assert_eq!((buf.padded_size(), buf.alignment()), (16, 16));
+        assert_eq!(buf.padding_mode(), Padding::Padded(16));
+        assert_eq!(buf.as_byte_ptr() as usize % 16, 0);
+        buf[2] = 9;
+        assert_eq!(buf.data()[32], 9u16.to_ne_bytes()[0]);
+        match BufferBuilder::<u64>::new().capacity(1).align(4).build() {
+            Err(Error::InvalidAlignment) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_build_huge_page_aligned_buffer_with_padding() {
+        let buf = BufferBuilder::<u64>::new()
+            .capacity(8)
+            .padding(Padding::Pow2)
+            .huge_pages(true)
+            .build()
+            .unwrap();
+        assert_eq!(buf.as_byte_ptr() as usize % (2 * 1024 * 1024), 0);
+        assert_eq!(buf.padded_size(), 8);
+    }
+
+    #[test]
+    fn should_default_to_empty_packed_buffer() {
+        let buf = BufferBuilder::<u32>::default().build().unwrap();
+        assert_eq!((buf.cap(), buf.padded_size()), (0, 4));
+        assert_eq!(Buffer::<u32>::builder().capacity(2).build().unwrap().cap(), 2);
+    }
+}
@@ -0,0 +1,88 @@
+use core::convert::TryFrom;
+use core::ops::{ Deref, DerefMut };
+use core::slice;
+
+use allocator_api2::alloc::{ Allocator, Global };
+
+use { Buffer, Error };
+
+/// Buffer known to have no padding between entries, so it derefs to a native `[T]`
+/// and every slice method is available on it directly.
+pub struct ContiguousBuffer<'a, T: 'a, A: Allocator = Global> {
+    buffer: Buffer<'a, T, A>,
+}
+
+impl <'a, T: 'a> ContiguousBuffer<'a, T> {
+    pub fn new(cap: usize) -> Result<Self, Error> {
+        Ok(ContiguousBuffer {
+            buffer: Buffer::new(cap)?,
+        })
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> ContiguousBuffer<'a, T, A> {
+    pub fn as_buffer(&self) -> &Buffer<'a, T, A> {
+        &self.buffer
+    }
+
+    pub fn into_inner(self) -> Buffer<'a, T, A> {
+        self.buffer
+    }
+}
+
+/// Hands the buffer back when it is padded
+impl <'a, T: 'a, A: Allocator> TryFrom<Buffer<'a, T, A>> for ContiguousBuffer<'a, T, A> {
+    type Error = Buffer<'a, T, A>;
+
+    fn try_from(buffer: Buffer<'a, T, A>) -> Result<Self, Self::Error> {
+        if !buffer.is_contiguous() {
+            return Err(buffer);
+        }
+        Ok(ContiguousBuffer { buffer })
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> Deref for ContiguousBuffer<'a, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe {
+            slice::from_raw_parts(self.buffer.ptr as *const T, self.buffer.cap)
+        }
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> DerefMut for ContiguousBuffer<'a, T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe {
+            slice::from_raw_parts_mut(self.buffer.ptr as *mut T, self.buffer.cap)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_use_slice_methods_through_deref() {
+        let mut buf = ContiguousBuffer::<u32>::new(4).unwrap();
+        buf.copy_from_slice(&[4, 3, 2, 1]);
+        assert_eq!(buf.iter().sum::<u32>(), 10);
+        assert_eq!(buf.first(), Some(&4));
+        assert_eq!(&buf[1..3], &[3, 2]);
+        buf.sort();
+        assert_eq!(&buf[..], &[1, 2, 3, 4]);
+        assert_eq!(buf.as_buffer()[0], 1);
+    }
+
+    #[test]
+    fn should_only_adopt_unpadded_buffers() {
+        let buf = ContiguousBuffer::try_from(Buffer::<u16>::pow2(3).unwrap()).unwrap();
+        assert_eq!(buf.len(), 3);
+        let padded = Buffer::<u16>::padded(3, 4).unwrap();
+        let padded = ContiguousBuffer::try_from(padded).err().unwrap();
+        assert_eq!(padded.padded_size(), 4);
+        assert!(ContiguousBuffer::<u32>::new(0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use core::marker::PhantomData;
+use core::ptr;
+
+use { get_bit, set_bit, slot_offset };
+
+/// Sequential writer over a buffer's slots, filling them in order from the first.
+/// It holds the buffer's exclusive borrow, so nothing else can touch the slots while it fills them.
+pub struct Cursor<'b, T: 'b> {
+    ptr: *mut u8,
+    cap: usize,
+    padded_size: usize,
+    position: usize,
+    // A tracked buffer's bits, so pushed values are dropped along with the buffer
+    initialized: Option<&'b mut [u64]>,
+    _marker: PhantomData<&'b mut T>,
+}
+
+// Callers guarantee `ptr` addresses `cap` slots that stay exclusively borrowed for `'b`, and that
+// `initialized` holds a bit for each of them
+pub(crate) unsafe fn cursor<'b, T>(ptr: *mut u8, cap: usize, padded_size: usize, initialized: Option<&'b mut [u64]>) -> Cursor<'b, T> {
+    Cursor {
+        ptr,
+        cap,
+        padded_size,
+        position: 0,
+        initialized,
+        _marker: PhantomData,
+    }
+}
+
+impl <'b, T: 'b> Cursor<'b, T> {
+    /// Index of the next slot `push` writes
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cap - self.position
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.position == self.cap
+    }
+
+    /// Writes `value` into the next slot, handing it back once every slot is written.
+    /// The previous contents of the slot are overwritten without being dropped, unless the buffer
+    /// is tracked and holds a value there, which is dropped like `insert` does.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        let slot = unsafe {
+            self.ptr.add(slot_offset(self.position, self.padded_size)) as *mut T
+        };
+        if let Some(bits) = self.initialized.as_mut() {
+            if get_bit(bits, self.position) {
+                unsafe {
+                    ptr::drop_in_place(slot)
+                }
+            }
+            set_bit(bits, self.position, true);
+        }
+        unsafe {
+            ptr::write(slot, value)
+        }
+        self.position += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+
+    use Buffer;
+
+    #[test]
+    fn should_push_until_full_then_report_overflow() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        {
+            let mut cursor = buf.cursor_mut();
+            assert_eq!((cursor.position(), cursor.remaining()), (0, 3));
+            for value in 1..4 {
+                cursor.push(value).unwrap();
+            }
+            assert!(cursor.is_full());
+            assert_eq!(cursor.push(4), Err(4));
+            assert_eq!((cursor.position(), cursor.remaining()), (3, 0));
+        }
+        assert_eq!(buf.to_contiguous(), vec![1, 2, 3]);
+        assert_eq!(buf.data_size(), 24);
+        assert!(buf.data().chunks(8).all(|slot| slot[4..] == [0; 4])); // Padding untouched
+    }
+
+    #[test]
+    fn should_track_pushed_values_and_drop_overwritten_ones() {
+        let first = Rc::new(());
+        let second = Rc::new(());
+        {
+            let mut buf = Buffer::<Rc<()>>::tracked(2).unwrap();
+            buf.insert(0, first.clone());
+            let mut cursor = buf.cursor_mut();
+            cursor.push(second.clone()).unwrap();
+            cursor.push(second.clone()).unwrap();
+            assert_eq!((Rc::strong_count(&first), Rc::strong_count(&second)), (1, 3));
+        }
+        assert_eq!(Rc::strong_count(&second), 1);
+    }
+
+    #[test]
+    fn should_report_full_cursor_over_empty_buffer() {
+        let mut buf = Buffer::<u32>::new(0).unwrap();
+        let mut cursor = buf.cursor_mut();
+        assert!(cursor.is_full());
+        assert_eq!(cursor.push(1), Err(1));
+    }
+}
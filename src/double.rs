@@ -0,0 +1,244 @@
+use core::cell::UnsafeCell;
+#[cfg(not(feature = "std"))]
+use core::hint;
+use core::ops::{ Deref, DerefMut };
+use core::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+
+use { Buffer, Error };
+
+/// Front and back buffers of the same layout, presenting the back by flipping an index.
+///
+/// Writers fill `back_mut` while readers look at `front`, then `swap` makes the new data the front
+/// without copying. `copy_front_to_back` seeds the back when only part of it is rewritten each round.
+pub struct DoubleBuffer<T: 'static> {
+    buffers: [Buffer<'static, T>; 2],
+    front: usize,
+}
+
+impl <T: 'static> DoubleBuffer<T> {
+    pub fn new(cap: usize) -> Result<Self, Error> {
+        Self::from_buffers(Buffer::new(cap)?, Buffer::new(cap)?)
+    }
+
+    /// Fails with `LayoutMismatch` unless both buffers share `cap` and `padded_size`
+    pub fn from_buffers(front: Buffer<'static, T>, back: Buffer<'static, T>) -> Result<Self, Error> {
+        if front.cap() != back.cap() || front.padded_size() != back.padded_size() {
+            return Err(Error::LayoutMismatch);
+        }
+        Ok(DoubleBuffer {
+            buffers: [front, back],
+            front: 0,
+        })
+    }
+
+    pub fn front(&self) -> &Buffer<'static, T> {
+        &self.buffers[self.front]
+    }
+
+    pub fn back(&self) -> &Buffer<'static, T> {
+        &self.buffers[1 - self.front]
+    }
+
+    pub fn back_mut(&mut self) -> &mut Buffer<'static, T> {
+        &mut self.buffers[1 - self.front]
+    }
+
+    /// Presents the back buffer, the old front becomes the next back
+    pub fn swap(&mut self) {
+        self.front = 1 - self.front;
+    }
+
+    pub fn copy_front_to_back(&mut self) where T: Copy {
+        let (first, second) = self.buffers.split_at_mut(1);
+        let (front, back) = if self.front == 0 {
+            (&first[0], &mut second[0])
+        } else {
+            (&second[0], &mut first[0])
+        };
+        back.copy_from(front)
+            .expect("double buffers always share a layout");
+    }
+}
+
+/// `DoubleBuffer` that can be shared between threads, readers see the front while one writer fills the back.
+///
+/// `front` never blocks. `back_mut` hands out the back to a single writer at a time and first waits for
+/// readers still holding the previous front, so they never observe a write in progress.
+pub struct SyncDoubleBuffer<T: 'static> {
+    buffers: [UnsafeCell<Buffer<'static, T>>; 2],
+    front: AtomicUsize,
+    // Readers currently holding each buffer
+    readers: [AtomicUsize; 2],
+    writing: AtomicBool,
+}
+
+unsafe impl<T: Send + Sync + 'static> Sync for SyncDoubleBuffer<T> {}
+
+impl <T: 'static> SyncDoubleBuffer<T> {
+    pub fn new(cap: usize) -> Result<Self, Error> {
+        Ok(SyncDoubleBuffer {
+            buffers: [UnsafeCell::new(Buffer::new(cap)?), UnsafeCell::new(Buffer::new(cap)?)],
+            front: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writing: AtomicBool::new(false),
+        })
+    }
+
+    pub fn front(&self) -> FrontGuard<'_, T> {
+        loop {
+            let index = self.front.load(Ordering::SeqCst);
+            self.readers[index].fetch_add(1, Ordering::SeqCst);
+            // A swap in between may have handed this buffer to the writer, so only keep it if it's still the front
+            if self.front.load(Ordering::SeqCst) == index {
+                return FrontGuard { owner: self, index };
+            }
+            self.readers[index].fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Exclusive access to the back buffer, `None` while another writer holds it
+    pub fn back_mut(&self) -> Option<BackGuard<'_, T>> {
+        if self.writing.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            return None;
+        }
+        let index = 1 - self.front.load(Ordering::SeqCst);
+        while self.readers[index].load(Ordering::SeqCst) != 0 {
+            #[cfg(feature = "std")]
+            ::std::thread::yield_now(); // Lets a preempted reader finish on a busy core
+            #[cfg(not(feature = "std"))]
+            hint::spin_loop();
+        }
+        Some(BackGuard { owner: self, index })
+    }
+}
+
+/// Read access to the front buffer of a `SyncDoubleBuffer`
+pub struct FrontGuard<'d, T: 'static> {
+    owner: &'d SyncDoubleBuffer<T>,
+    index: usize,
+}
+
+impl <'d, T: 'static> Deref for FrontGuard<'d, T> {
+    type Target = Buffer<'static, T>;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            &*self.owner.buffers[self.index].get()
+        }
+    }
+}
+
+impl <'d, T: 'static> Drop for FrontGuard<'d, T> {
+    fn drop(&mut self) {
+        self.owner.readers[self.index].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Write access to the back buffer of a `SyncDoubleBuffer`, dropping it without `swap` keeps the front
+pub struct BackGuard<'d, T: 'static> {
+    owner: &'d SyncDoubleBuffer<T>,
+    index: usize,
+}
+
+impl <'d, T: 'static> BackGuard<'d, T> {
+    /// Presents what was written as the new front
+    pub fn swap(self) {
+        self.owner.front.store(self.index, Ordering::SeqCst);
+    }
+}
+
+impl <'d, T: 'static> Deref for BackGuard<'d, T> {
+    type Target = Buffer<'static, T>;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            &*self.owner.buffers[self.index].get()
+        }
+    }
+}
+
+impl <'d, T: 'static> DerefMut for BackGuard<'d, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            &mut *self.owner.buffers[self.index].get()
+        }
+    }
+}
+
+impl <'d, T: 'static> Drop for BackGuard<'d, T> {
+    fn drop(&mut self) {
+        self.owner.writing.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn should_present_back_buffer_on_swap() {
+        let mut double = DoubleBuffer::<u32>::new(3).unwrap();
+        let front = double.front().as_byte_ptr();
+        double.back_mut().fill(7);
+        assert_eq!(double.front()[0], 0);
+        double.swap();
+        assert_eq!(Some(&[7, 7, 7][..]), double.front().as_slice());
+        assert_eq!(double.back().as_byte_ptr(), front);
+    }
+
+    #[test]
+    fn should_seed_back_from_front() {
+        let mut double = DoubleBuffer::<u32>::new(2).unwrap();
+        double.back_mut().fill(3);
+        double.swap();
+        double.copy_front_to_back();
+        assert_eq!(double.back(), double.front());
+    }
+
+    #[test]
+    fn should_reject_buffers_of_other_layouts() {
+        let front = Buffer::<u32>::new(2).unwrap();
+        let back = Buffer::<u32>::padded(2, 8).unwrap();
+        assert!(DoubleBuffer::from_buffers(front, back).is_err());
+    }
+
+    #[test]
+    fn should_allow_one_writer_at_a_time() {
+        let double = SyncDoubleBuffer::<u32>::new(2).unwrap();
+        let back = double.back_mut().unwrap();
+        assert!(double.back_mut().is_none());
+        back.swap();
+        assert!(double.back_mut().is_some());
+    }
+
+    #[test]
+    fn should_only_show_readers_complete_generations() {
+        const ROUNDS: u32 = 1_000;
+        let double = SyncDoubleBuffer::<u32>::new(64).unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for generation in 1..=ROUNDS {
+                    let mut back = double.back_mut().unwrap();
+                    back.fill(generation);
+                    back.swap();
+                }
+            });
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    let mut last = 0;
+                    while last < ROUNDS {
+                        let front = double.front();
+                        let generation = front[0];
+                        assert!(front.iter().all(|&value| value == generation));
+                        assert!(generation >= last);
+                        last = generation;
+                        drop(front);
+                        thread::yield_now();
+                    }
+                });
+            }
+        });
+        assert_eq!(double.front()[63], ROUNDS);
+    }
+}
@@ -0,0 +1,246 @@
+use core::marker::PhantomData;
+use core::mem::{ self, MaybeUninit };
+use core::ops::{ Index, IndexMut };
+use core::slice;
+
+use { base_align, cache_line_size, padded_stride, slot_offset, Error, Iter, IterMut, Padding };
+
+// Zero sized marker that starts the slots on a cache line, so every padding gets the base alignment
+// it has on the heap. Mirrors `CACHE_LINE_SIZE`, which `repr(align)` can't take directly.
+#[cfg_attr(any(all(target_arch = "aarch64", target_vendor = "apple"), target_arch = "powerpc64"), repr(align(128)))]
+#[cfg_attr(target_arch = "s390x", repr(align(256)))]
+#[cfg_attr(any(
+    target_arch = "arm",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "riscv32",
+), repr(align(32)))]
+#[cfg_attr(not(any(
+    all(target_arch = "aarch64", target_vendor = "apple"),
+    target_arch = "powerpc64",
+    target_arch = "s390x",
+    target_arch = "arm",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "riscv32",
+)), repr(align(64)))]
+#[derive(Clone, Copy)]
+struct CacheLine;
+
+const _: () = assert!(mem::align_of::<CacheLine>() == cache_line_size());
+
+// `N` slots of `STRIDE` bytes each
+#[repr(C)]
+struct Slots<const N: usize, const STRIDE: usize> {
+    _line: [CacheLine; 0],
+    bytes: [[MaybeUninit<u8>; STRIDE]; N],
+}
+
+/// Fixed capacity buffer of `N` slots stored inline, for small sizes known at compile time.
+///
+/// Slots are zeroed and strided like a heap `Buffer` with the same `Padding`. `STRIDE` is the stride
+/// in bytes, which must be the one `padded_stride::<T>(padding)` works out, so both containers accept
+/// and reject the same layouts. The slots start on a cache line. Nothing is allocated, so nothing is
+/// freed on drop either.
+pub struct InlineBuffer<T, const N: usize, const STRIDE: usize> {
+    slots: Slots<N, STRIDE>,
+    padding: Padding,
+    _marker: PhantomData<T>,
+}
+
+impl <T, const N: usize, const STRIDE: usize> InlineBuffer<T, N, STRIDE> {
+    /// Packed slots, `STRIDE` must be `size_of::<T>()`
+    pub fn new() -> Result<Self, Error> {
+        Self::with_padding(Padding::None)
+    }
+
+    /// Fails with `LayoutMismatch` when `STRIDE` isn't the stride `padding` gives `T`
+    pub fn with_padding(padding: Padding) -> Result<Self, Error> {
+        if padded_stride::<T>(padding)? != STRIDE {
+            return Err(Error::LayoutMismatch);
+        }
+        if base_align::<T>(padding) > cache_line_size() {
+            return Err(Error::InvalidAlignment);
+        }
+        Ok(InlineBuffer {
+            slots: Slots {
+                _line: [],
+                bytes: [[MaybeUninit::new(0); STRIDE]; N],
+            },
+            padding,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn cap(&self) -> usize {
+        N
+    }
+
+    pub fn size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    pub fn padded_size(&self) -> usize {
+        STRIDE
+    }
+
+    pub fn data_size(&self) -> usize {
+        N * STRIDE
+    }
+
+    pub fn padding_mode(&self) -> Padding {
+        self.padding
+    }
+
+    /// Offset in bytes of a slot from the first one, `index` must be below `cap`
+    pub fn byte_offset(&self, index: usize) -> usize {
+        debug_assert!(index < N, "index out of bounds: the cap is {} but the index is {}", N, index);
+        slot_offset(index, STRIDE)
+    }
+
+    fn base(&self) -> *const u8 {
+        self.slots.bytes.as_ptr() as *const u8
+    }
+
+    fn base_mut(&mut self) -> *mut u8 {
+        self.slots.bytes.as_mut_ptr() as *mut u8
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= N {
+            return None;
+        }
+        Some(unsafe {
+            &*(self.base().add(slot_offset(index, STRIDE)) as *const T)
+        })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= N {
+            return None;
+        }
+        Some(unsafe {
+            &mut *(self.base_mut().add(slot_offset(index, STRIDE)) as *mut T)
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            ptr: self.base() as *mut u8,
+            index: 0,
+            cap: N,
+            padded_size: STRIDE,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            ptr: self.base_mut(),
+            index: 0,
+            cap: N,
+            padded_size: STRIDE,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(self.base(), self.data_size())
+        }
+    }
+}
+
+impl <T, const N: usize, const STRIDE: usize> Index<usize> for InlineBuffer<T, N, STRIDE> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", N, index))
+    }
+}
+
+impl <T, const N: usize, const STRIDE: usize> IndexMut<usize> for InlineBuffer<T, N, STRIDE> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", N, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Buffer;
+
+    const LINE: usize = cache_line_size();
+
+    #[test]
+    fn should_behave_like_heap_buffer() {
+        let mut inline = InlineBuffer::<u32, 4, 4>::new().unwrap();
+        let mut heap = Buffer::<u32>::new(4).unwrap();
+        assert_eq!((inline.cap(), inline.size(), inline.padded_size(), inline.data_size()),
+            (heap.cap(), heap.size(), heap.padded_size(), heap.data_size()));
+        for i in 0..4 {
+            inline[i] = i as u32 * 3;
+            heap[i] = i as u32 * 3;
+        }
+        *inline.get_mut(1).unwrap() += 1;
+        *heap.get_mut(1).unwrap() += 1;
+        assert!(inline.iter().eq(heap.iter()));
+        assert_eq!(inline.data(), heap.data());
+        assert_eq!(inline.get(4), None);
+    }
+
+    #[test]
+    fn should_stride_padded_slots() {
+        let mut inline = InlineBuffer::<u32, 2, 8>::with_padding(Padding::Padded(8)).unwrap();
+        assert_eq!(inline.padded_size(), 8);
+        for entry in inline.iter_mut() {
+            *entry = 0x0101_0101;
+        }
+        assert_eq!(inline.data(), &[1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0]);
+    }
+
+    fn assert_same_layout<T, const N: usize, const STRIDE: usize>(padding: Padding) {
+        let inline = InlineBuffer::<T, N, STRIDE>::with_padding(padding).unwrap();
+        let heap = Buffer::<T>::with_padding(N, padding).unwrap();
+        assert_eq!((inline.padded_size(), inline.data_size(), inline.padding_mode()),
+            (heap.padded_size(), heap.data_size(), heap.padding_mode()));
+        for i in 0..N {
+            assert_eq!(inline.byte_offset(i), heap.byte_offset(i));
+        }
+        assert_eq!(inline.base() as usize % heap.alignment(), 0);
+    }
+
+    #[test]
+    fn should_stride_like_heap_buffer_for_every_padding() {
+        assert_same_layout::<u16, 3, 6>(Padding::Padded(6));
+        assert_same_layout::<u32, 3, LINE>(Padding::CacheAligned);
+        assert_same_layout::<[u8; 5], 3, 8>(Padding::Pow2);
+        assert_same_layout::<[u8; 70], 2, { 70usize.div_ceil(LINE) * LINE }>(Padding::Isolated);
+    }
+
+    #[test]
+    fn should_reject_layouts_heap_buffers_reject() {
+        match (InlineBuffer::<u32, 3, 6>::with_padding(Padding::Padded(6)), Buffer::<u32>::padded(3, 6)) {
+            (Err(Error::MisalignedPadding { .. }), Err(Error::MisalignedPadding { .. })) => {},
+            results => panic!("unexpected results: {:?}", (results.0.is_ok(), results.1)),
+        }
+        match InlineBuffer::<u16, 3, 8>::with_padding(Padding::Padded(6)) {
+            Err(Error::LayoutMismatch) => {},
+            result => panic!("unexpected result: {:?}", result.is_ok()),
+        }
+    }
+
+    #[test]
+    fn should_reject_zero_sized_slots() {
+        assert!(InlineBuffer::<(), 4, 0>::new().is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_indexing_past_cap() {
+        let inline = InlineBuffer::<u8, 2, 1>::new().unwrap();
+        let _ = inline[2];
+    }
+}
@@ -0,0 +1,181 @@
+use std::cmp::min;
+use std::io::{ self, Read, Seek, SeekFrom, Write };
+
+use allocator_api2::alloc::Allocator;
+
+use Buffer;
+
+/// `io::Read` and `io::Seek` over a buffer's bytes, padding included, from an internal cursor
+pub struct BufferReader<'b> {
+    data: &'b [u8],
+    position: usize,
+}
+
+impl <'b> BufferReader<'b> {
+    pub fn new<'a, T: 'a, A: Allocator>(buffer: &'b Buffer<'a, T, A>) -> Self {
+        BufferReader {
+            data: buffer.data(),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+}
+
+impl <'b> Read for BufferReader<'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = min(self.remaining(), buf.len());
+        if len > 0 {
+            buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+            self.position += len;
+        }
+        Ok(len)
+    }
+}
+
+impl <'b> Seek for BufferReader<'b> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i64),
+            SeekFrom::End(offset) => (self.data.len() as i64, offset),
+            SeekFrom::Current(offset) => (self.position as i64, offset),
+        };
+        match base.checked_add(offset) {
+            Some(position) if position >= 0 => {
+                self.position = position as usize;
+                Ok(position as u64)
+            },
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")),
+        }
+    }
+}
+
+/// `io::Write` over a buffer's bytes, padding included, continuing from an internal cursor.
+/// Writes are cut short once the end of the allocation is reached.
+pub struct BufferWriter<'b> {
+    data: &'b mut [u8],
+    position: usize,
+}
+
+impl <'b> BufferWriter<'b> {
+    pub fn new<'a, T: 'a, A: Allocator>(buffer: &'b mut Buffer<'a, T, A>) -> Self {
+        BufferWriter {
+            data: buffer.data_mut(),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+}
+
+impl <'b> Write for BufferWriter<'b> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = min(self.remaining(), buf.len());
+        if len > 0 {
+            self.data[self.position..self.position + len].copy_from_slice(&buf[..len]);
+            self.position += len;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_back_pattern_in_small_chunks() {
+        let mut buf = Buffer::<u8>::new(10).unwrap();
+        buf.fill_with(|i| i as u8 * 3);
+        let mut reader = buf.reader();
+        let mut pattern = Vec::new();
+        let mut chunk = [0u8; 3];
+        loop {
+            match reader.read(&mut chunk).unwrap() {
+                0 => break,
+                len => pattern.extend_from_slice(&chunk[..len]),
+            }
+        }
+        assert_eq!(&pattern[..], buf.data());
+        assert_eq!(reader.read(&mut chunk).unwrap(), 0);
+    }
+
+    #[test]
+    fn should_seek_within_buffer() {
+        let mut buf = Buffer::<u8>::new(4).unwrap();
+        buf.copy_from_slice(&[1, 2, 3, 4]).unwrap();
+        let mut reader = BufferReader::new(&buf);
+        let mut byte = [0u8; 1];
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 3);
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(byte, [4]);
+        assert_eq!(reader.seek(SeekFrom::Current(-3)).unwrap(), 1);
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(byte, [2]);
+        assert_eq!(reader.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(reader.read(&mut byte).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-11)).is_err());
+    }
+
+    #[test]
+    fn should_copy_buffer_into_writer_of_another_buffer() {
+        let mut src = Buffer::<u32>::new(2).unwrap();
+        src.copy_from_slice(&[0x0403_0201, 0x0807_0605]).unwrap();
+        let mut dst = Buffer::<u8>::new(8).unwrap();
+        assert_eq!(io::copy(&mut src.reader(), &mut dst.writer()).unwrap(), 8);
+        assert_eq!(src.data(), dst.data());
+    }
+
+    #[test]
+    fn should_fill_buffer_with_formatted_writes() {
+        let mut buf = Buffer::<u8>::new(8).unwrap();
+        {
+            let mut writer = buf.writer();
+            write!(writer, "ab{}", 12).unwrap();
+            assert_eq!(writer.position(), 4);
+            write!(writer, "cdef").unwrap();
+            assert_eq!(writer.remaining(), 0);
+        }
+        assert_eq!(b"ab12cdef", buf.data());
+    }
+
+    #[test]
+    fn should_report_write_zero_once_capacity_is_exhausted() {
+        let mut buf = Buffer::<u8>::new(3).unwrap();
+        let mut writer = BufferWriter::new(&mut buf);
+        assert_eq!(writer.write(b"abcd").unwrap(), 3);
+        assert_eq!(writer.write(b"e").unwrap(), 0);
+        assert_eq!(writer.write_all(b"e").unwrap_err().kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn should_continue_from_set_position() {
+        let mut buf = Buffer::<u8>::padded(2, 2).unwrap();
+        {
+            let mut writer = buf.writer();
+            writer.set_position(2);
+            writer.write_all(&[9, 8]).unwrap();
+        }
+        assert_eq!(&[0, 0, 9, 8], buf.data());
+    }
+}
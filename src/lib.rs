@@ -1,270 +1,4326 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
 extern crate core;
 extern crate log;
+extern crate allocator_api2;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "std")]
 extern crate memsec;
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(feature = "bytes")]
+pub mod buf;
+pub mod builder;
+pub mod contiguous;
+pub mod cursor;
+pub mod double;
+pub mod inline;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod pool;
+pub mod ring;
+pub mod seqlock;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod view;
 
-// TODO: more robust cache line detection
-// #[cfg(target_pointer_width = "32")]
-// const CACHE_LINE_SIZE: usize = 56;
-// #[cfg(target_pointer_width = "64")]
+#[cfg(feature = "bytes")]
+pub use buf::{ BufferBuf, BufferBufMut };
+pub use builder::BufferBuilder;
+pub use contiguous::ContiguousBuffer;
+pub use cursor::Cursor;
+pub use double::{ DoubleBuffer, SyncDoubleBuffer };
+pub use inline::InlineBuffer;
+#[cfg(feature = "std")]
+pub use io::{ BufferReader, BufferWriter };
+#[cfg(feature = "metrics")]
+pub use metrics::{ allocated_bytes, live_buffers, AllocStats };
+#[cfg(feature = "rayon")]
+pub use par::{ ParIter, ParIterMut };
+pub use pool::{ BufferPool, PooledBuffer };
+pub use ring::SpscRing;
+pub use seqlock::SeqlockBuffer;
+pub use view::{ BufferView, BufferViewMut };
+
+// Cache line sizes per architecture, anything not listed falls back to 64
+#[cfg(all(target_arch = "aarch64", target_vendor = "apple"))]
+const CACHE_LINE_SIZE: usize = 128;
+#[cfg(target_arch = "powerpc64")]
+const CACHE_LINE_SIZE: usize = 128;
+#[cfg(target_arch = "s390x")]
+const CACHE_LINE_SIZE: usize = 256;
+#[cfg(any(
+    target_arch = "arm",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "riscv32",
+))]
+const CACHE_LINE_SIZE: usize = 32;
+#[cfg(not(any(
+    all(target_arch = "aarch64", target_vendor = "apple"),
+    target_arch = "powerpc64",
+    target_arch = "s390x",
+    target_arch = "arm",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "riscv32",
+)))]
 const CACHE_LINE_SIZE: usize = 64;
 
-use std::cmp::{ max };
-use std::alloc::{ alloc_zeroed, dealloc, Layout, LayoutError };
-use std::mem;
+// 64 bit FNV-1a parameters used by `Buffer::checksum`
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Bytes reserved after the entries by `Buffer::with_canary`
+pub const CANARY_LEN: usize = 16;
+const CANARY: u8 = 0xA5;
+
+// Alignments from a page up are served by the platform's aligned allocator rather than `Global`,
+// which may over-allocate to honour them
+#[cfg(any(unix, windows))]
+const MEMALIGN_THRESHOLD: usize = 4096;
+
+#[cfg(target_os = "linux")]
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+// Memory policy constants from linux/mempolicy.h, which libc does not expose
+#[cfg(all(feature = "numa", target_os = "linux"))]
+const MPOL_BIND: libc::c_int = 2;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+const MPOL_MF_STRICT: libc::c_uint = 1;
+
+/// Cache line size assumed for the target when padding with `cache_aligned`
+pub const fn cache_line_size() -> usize {
+    CACHE_LINE_SIZE
+}
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::{ max, min, Ordering };
+use core::convert::TryFrom;
+use core::error;
+use core::fmt;
+use core::hash::{ Hash, Hasher };
+use core::marker::PhantomData;
+use core::alloc::{ Layout, LayoutError };
+use alloc::alloc::handle_alloc_error;
+use allocator_api2::alloc::{ Allocator, Global };
+use core::mem::{ self, ManuallyDrop, MaybeUninit };
+use core::ptr::{ self, NonNull };
+use core::ops::{ Index, IndexMut, Range };
+use core::slice;
 
 #[derive(Debug)]
 pub enum Error {
+    AdviseFailed,
     AllocCapacityOverflow,
     BufferSizeOverflow,
+    CapacityExceeded,
+    CapacityTooSmall,
+    IncompatibleCast,
+    IndexOutOfBounds,
     InsufficientMemory,
+    InvalidAlignment,
     LayoutError(LayoutError),
+    LayoutMismatch,
+    LockFailed,
+    MapFailed,
+    MisalignedPadding { padded_size: usize, align: usize },
+    NumaUnavailable,
+    SharedMemoryFailed,
+    SliceTooShort,
     ZeroBufferNotSupported,
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::AdviseFailed => write!(f, "failed to apply paging advice to the allocation"),
+            Error::AllocCapacityOverflow => write!(f, "allocation exceeds the maximum size supported by the platform"),
+            Error::BufferSizeOverflow => write!(f, "buffer size overflows usize"),
+            Error::CapacityExceeded => write!(f, "buffer capacity exceeded"),
+            Error::CapacityTooSmall => write!(f, "requested capacity is smaller than the current capacity"),
+            Error::IncompatibleCast => write!(f, "entry type is incompatible with the buffer's alignment or stride"),
+            Error::IndexOutOfBounds => write!(f, "index is beyond the buffer's capacity"),
+            Error::InsufficientMemory => write!(f, "insufficient memory to allocate buffer"),
+            Error::InvalidAlignment => write!(f, "alignment must be a power of two no smaller than the entry's alignment"),
+            Error::LayoutError(ref error) => write!(f, "invalid buffer layout: {}", error),
+            Error::LayoutMismatch => write!(f, "buffers differ in capacity or stride"),
+            Error::LockFailed => write!(f, "failed to lock buffer memory"),
+            Error::MapFailed => write!(f, "failed to map the backing file"),
+            Error::MisalignedPadding { padded_size, align } => write!(f, "padded size {} is not a multiple of alignment {}", padded_size, align),
+            Error::NumaUnavailable => write!(f, "numa node binding is not available"),
+            Error::SharedMemoryFailed => write!(f, "failed to open or map the shared memory object"),
+            Error::SliceTooShort => write!(f, "slice is shorter than an entry"),
+            Error::ZeroBufferNotSupported => write!(f, "zero sized buffers are not supported"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::LayoutError(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
 impl From<LayoutError> for Error {
     fn from(error: LayoutError) -> Self {
         Error::LayoutError(error)
     }
 }
 
-#[derive(Debug)]
-pub struct Buffer<'a, T: 'a> {
+/// Expected access pattern for the pages of an allocation, passed on to `madvise`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Advice {
+    /// The pages will be needed soon, so read ahead
+    WillNeed,
+    /// The pages aren't needed for now, their physical memory can be reclaimed
+    DontNeed,
+    /// The pages will be read in order, so read ahead aggressively and free them soon after
+    Sequential,
+    /// The pages will be read in no particular order, so read ahead is wasted
+    Random,
+}
+
+/// C compatible view of a buffer's layout, entry `i` is the `len` bytes at `ptr + i * stride`.
+/// It borrows nothing, so it must not outlive the buffer it was taken from.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferFfi {
+    pub ptr: *mut u8,
+    pub len: usize,
+    pub stride: usize,
+    pub count: usize,
+}
+
+/// How long a prefetched cache line is expected to stay useful, nearest cache level first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locality {
+    T0,
+    T1,
+    T2,
+    /// Non temporal, the line is used once and shouldn't displace other data
+    Nta,
+}
+
+pub struct Buffer<'a, T: 'a, A: Allocator = Global> {
+    alloc: A,
     layout: Layout,
     ptr: *mut u8,
     cap: usize,
     size: usize,
     padded_size: usize,
-    pub entries: Vec<&'a mut T>,
+    data_size: usize,
+    #[cfg(feature = "std")]
+    secure: bool,
+    #[cfg(feature = "std")]
+    locked: bool,
+    padding: Padding,
+    // Bytes of `CANARY` kept right after the data region, zero when there is no canary
+    canary: usize,
+    // Slots in use through `push` and `pop`, the raw `cap` based accessors ignore it
+    len: usize,
+    // One bit per slot holding a value that must be dropped, only kept by `tracked` buffers
+    initialized: Option<Vec<u64>>,
+    // Slots marked with `set_occupied`, one bit each, left empty until a slot is first marked
+    occupancy: Vec<u64>,
+    backing: Backing,
+    // Entries borrow from the buffer on demand, `'a` only bounds how long `T` must live
+    _marker: PhantomData<&'a mut T>,
 }
 
-fn buffer_from<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize) -> Result<Buffer<'a, T>, Error> {
-    let align = mem::align_of::<T>();
+// Where the allocation came from, so `Drop` can release it the matching way
+#[derive(Debug)]
+enum Backing {
+    Empty,
+    Heap,
+    #[cfg(target_os = "linux")]
+    Mapped,
+    #[cfg(feature = "mmap")]
+    File(memmap2::MmapMut),
+    // Named so the object can be unlinked later, unmapping never unlinks it
+    #[cfg(all(feature = "shm", unix))]
+    Shared(std::ffi::CString),
+    // The whole mapping, which starts before `ptr` and ends with the guard page
+    #[cfg(all(feature = "guard-pages", any(target_os = "linux", target_os = "macos")))]
+    Guarded { base: *mut u8, len: usize },
+    // From `posix_memalign`, or `_aligned_malloc` on Windows, freed with the matching function
+    #[cfg(any(unix, windows))]
+    Memalign,
+}
+
+#[cfg(windows)]
+extern "C" {
+    fn _aligned_malloc(size: usize, alignment: usize) -> *mut core::ffi::c_void;
+    fn _aligned_free(ptr: *mut core::ffi::c_void);
+}
+
+// Zeroed allocation from the platform's aligned allocator, its result is released by `Backing::Memalign`
+#[cfg(any(unix, windows))]
+fn buffer_memalign<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize) -> Result<Buffer<'a, T>, Error> {
     let layout = Layout::from_size_align(alloc_size, align)?;
-    // Heap allocation can yield undefined behavior if not checked to ensure non null pointer result
-    // https://specs.amethyst.rs/docs/api/nom/lib/std/alloc/trait.globalalloc#tymethod.alloc
-    let ptr = unsafe {
-        let raw_ptr = alloc_zeroed(layout); // Heap allocation
-        // See assertion example of non zero pointer:
-        // https://edp.fortanix.com/docs/api/std/alloc/fn.alloc_zeroed.html
-        if *(raw_ptr as *mut u16) != 0 {
-            return Err(Error::InsufficientMemory);
+    #[cfg(unix)]
+    let raw_ptr = unsafe {
+        let mut raw_ptr = ptr::null_mut();
+        match libc::posix_memalign(&mut raw_ptr, align, alloc_size) {
+            0 => raw_ptr as *mut u8,
+            _ => ptr::null_mut(),
         }
-        raw_ptr as *mut u8
     };
-    let mut entries: Vec<&mut T> = Vec::with_capacity(cap);
-    for i in 0..cap {
-        entries.push(
-            unsafe {
-                mem::transmute(ptr.add(i * padded_size))
-            }
-        );
+    #[cfg(windows)]
+    let raw_ptr = unsafe { _aligned_malloc(alloc_size, align) as *mut u8 };
+    if !raw_ptr.is_null() {
+        unsafe {
+            ptr::write_bytes(raw_ptr, 0, alloc_size)
+        }
+    }
+    let mut buffer = buffer_at(raw_ptr, layout, cap, size, padded_size, alloc_size, Global)?;
+    buffer.backing = Backing::Memalign;
+    Ok(buffer)
+}
+
+fn buffer_from<'a, T, A: Allocator>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize, alloc: A) -> Result<Buffer<'a, T, A>, Error> {
+    let layout = Layout::from_size_align(alloc_size, align)?;
+    let raw_ptr = alloc.allocate_zeroed(layout) // Heap allocation
+        .map(|ptr| ptr.cast::<u8>().as_ptr())
+        .unwrap_or(ptr::null_mut());
+    buffer_at(raw_ptr, layout, cap, size, padded_size, alloc_size, alloc)
+}
+
+// Zeroed allocation whose base is aligned to `align`, large alignments go to the platform's aligned allocator
+fn buffer_aligned<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize) -> Result<Buffer<'a, T>, Error> {
+    #[cfg(any(unix, windows))]
+    {
+        if align >= MEMALIGN_THRESHOLD {
+            return buffer_memalign(cap, size, padded_size, alloc_size, align);
+        }
+    }
+    buffer_from(cap, size, padded_size, alloc_size, align, Global)
+}
+
+// Maps `alloc_size` bytes of huge pages aligned to `align`, falling back to the heap when none are free
+#[cfg(target_os = "linux")]
+fn huge_page_from<'a, T>(cap: usize, size: usize, padded_size: usize, alloc_size: usize, align: usize) -> Result<Buffer<'a, T>, Error> {
+    let len = alloc_size.checked_add(align - 1)
+        .ok_or(Error::BufferSizeOverflow)? / align * align;
+    let layout = Layout::from_size_align(len, align)?;
+    let raw_ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB,
+            -1,
+            0,
+        )
+    };
+    if raw_ptr == libc::MAP_FAILED {
+        log::warn!("huge pages unavailable, falling back to heap allocation of {} bytes", alloc_size);
+        return buffer_from(cap, size, padded_size, alloc_size, align, Global);
+    }
+    let mut buffer = buffer_at(raw_ptr as *mut u8, layout, cap, size, padded_size, alloc_size, Global)?;
+    buffer.backing = Backing::Mapped;
+    Ok(buffer)
+}
+
+fn buffer_at<'a, T, A: Allocator>(ptr: *mut u8, layout: Layout, cap: usize, size: usize, padded_size: usize, data_size: usize, alloc: A) -> Result<Buffer<'a, T, A>, Error> {
+    // Allocators signal failure with a null pointer, which must be checked before anything reads through it
+    // https://doc.rust-lang.org/std/alloc/fn.alloc_zeroed.html
+    if ptr.is_null() {
+        log::debug!("buffer allocation failed: alloc_size={} align={}", layout.size(), layout.align());
+        return Err(Error::InsufficientMemory);
+    }
+    if layout.size() > 0 {
+        #[cfg(feature = "metrics")]
+        metrics::record_alloc(layout.size());
+        log::debug!("buffer allocated: cap={} size={} padded_size={} alloc_size={} align={}",
+            cap, size, padded_size, layout.size(), layout.align());
     }
     Ok(Buffer {
+        alloc,
         layout,
         ptr,
         cap,
         size,
         padded_size,
-        entries,
+        data_size,
+        #[cfg(feature = "std")]
+        secure: false,
+        #[cfg(feature = "std")]
+        locked: false,
+        padding: Padding::None,
+        canary: 0,
+        len: 0,
+        initialized: None,
+        occupancy: Vec::new(),
+        backing: Backing::Heap,
+        _marker: PhantomData,
     })
 }
 
-enum Padding {
+/// Strategy used to pick the stride between entries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// Entries are packed at `size_of::<T>()`
     None,
+    /// Entries are strided by the given size, raised to at least `size_of::<T>()`
     Padded (usize),
+    /// Each entry starts on its own cache line
     CacheAligned,
+    /// The stride is rounded up to the next power of two
+    Pow2,
+    /// Each entry occupies whole cache lines of its own, starting from a line aligned base,
+    /// so no two entries ever share a line
+    Isolated,
+}
+
+// Alignment the base pointer needs for the slots to honour `padding`
+fn base_align<T>(padding: Padding) -> usize {
+    match padding {
+        Padding::CacheAligned | Padding::Isolated => max(mem::align_of::<T>(), cache_line_size()),
+        _ => mem::align_of::<T>(),
+    }
+}
+
+// Words of a bit set with one bit per slot
+fn bit_words(cap: usize) -> usize {
+    cap.div_ceil(64)
+}
+
+fn get_bit(bits: &[u64], index: usize) -> bool {
+    bits[index / 64] & (1 << (index % 64)) != 0
+}
+
+fn set_bit(bits: &mut [u64], index: usize, value: bool) {
+    if value {
+        bits[index / 64] |= 1 << (index % 64);
+    } else {
+        bits[index / 64] &= !(1 << (index % 64));
+    }
+}
+
+// Fits a bit set to `cap` slots, clearing the bits of slots past the end
+fn resize_bits(bits: &mut Vec<u64>, cap: usize) {
+    bits.resize(bit_words(cap), 0);
+    if let Some(last) = bits.last_mut().filter(|_| !cap.is_multiple_of(64)) {
+        *last &= (1 << (cap % 64)) - 1;
+    }
+}
+
+// Zero capacity buffer that never allocates, like `Vec::new`
+fn empty<'a, T, A: Allocator>(size: usize, padded_size: usize, alloc: A) -> Buffer<'a, T, A> {
+    let align = mem::align_of::<T>();
+    let layout = Layout::from_size_align(0, align).expect("alignment of a type is always valid");
+    let dangling = NonNull::<T>::dangling().cast::<u8>().as_ptr();
+    let mut buffer = match buffer_at(dangling, layout, 0, size, padded_size, 0, alloc) {
+        Ok(buffer) => buffer,
+        Err(_) => unreachable!("dangling pointer is never null"),
+    };
+    buffer.backing = Backing::Empty;
+    buffer
+}
+
+/// Bytes `Buffer::<T>::padded(cap, padded_size)` would allocate, running the same overflow checks
+/// without allocating, so callers can check a request against a memory budget first
+pub fn reserve_bytes<T>(cap: usize, padded_size: usize) -> Result<usize, Error> {
+    plan::<T>(cap, Padding::Padded(padded_size))
+        .map(|(_, _, alloc_size)| alloc_size)
+}
+
+// Byte offset of slot `index` from the base pointer, shared by every container striding by `padded_size`
+#[inline]
+fn slot_offset(index: usize, padded_size: usize) -> usize {
+    index * padded_size
 }
 
-fn new<'a, T>(cap: usize, padding: Padding) -> Result<Buffer<'a, T>, Error> {
+/// Stride between the slots of a buffer of `T` strided for `padding`, the same one heap and inline
+/// buffers use. Fails like the constructors do for misaligned or zero strides.
+pub const fn padded_stride<T>(padding: Padding) -> Result<usize, Error> {
     let size = mem::size_of::<T>();
-    let padded_size: usize = match padding {
+    let padded_size = match padding {
         Padding::None => size,
         Padding::Padded(padded_size) => {
-            let padded_size = max(padded_size, size); // Must be at least as big as the things being contained
-            let padded_size = max(padded_size, 1); // Size must also be at least one
-            padded_size
+            // Must be at least as big as the things being contained, and at least one
+            let padded_size = if padded_size > size { padded_size } else { size };
+            if padded_size > 1 { padded_size } else { 1 }
         },
         Padding::CacheAligned => {
-            if size % CACHE_LINE_SIZE == 0 { // Naturally aligned
+            let line = CACHE_LINE_SIZE;
+            if size.is_multiple_of(line) { // Naturally aligned
                 size
             } else {
-                (size / CACHE_LINE_SIZE + 1) * CACHE_LINE_SIZE
+                (size / line + 1) * line
             }
-        }
+        },
+        Padding::Pow2 => {
+            // Zero sized types still occupy a one byte stride
+            match (if size > 1 { size } else { 1 }).checked_next_power_of_two() {
+                Some(padded_size) => padded_size,
+                None => return Err(Error::BufferSizeOverflow),
+            }
+        },
+        Padding::Isolated => {
+            let line = CACHE_LINE_SIZE;
+            let lines = size.div_ceil(line);
+            // Zero sized types still get a line of their own
+            match (if lines > 1 { lines } else { 1 }).checked_mul(line) {
+                Some(padded_size) => padded_size,
+                None => return Err(Error::BufferSizeOverflow),
+            }
+        },
     };
+    // Every slot after the first is only aligned if the stride is a multiple of the alignment
+    let align = mem::align_of::<T>();
+    if !padded_size.is_multiple_of(align) {
+        return Err(Error::MisalignedPadding { padded_size, align });
+    }
+    if padded_size == 0 { // Unpadded zero sized types have no stride to address entries by
+        return Err(Error::ZeroBufferNotSupported);
+    }
+    Ok(padded_size)
+}
+
+// Works out the entry size, stride and total allocation size for `cap` entries of `T`
+fn plan<T>(cap: usize, padding: Padding) -> Result<(usize, usize, usize), Error> {
+    let padded_size = padded_stride::<T>(padding)?;
     let alloc_size = cap.checked_mul(padded_size)
         .ok_or(Error::BufferSizeOverflow)
-        .and_then(alloc_guard)?;
-    if alloc_size == 0 {
-        return Err (Error::ZeroBufferNotSupported)
-    }
-    buffer_from::<T>(cap, size, padded_size, alloc_size)
+        .and_then(alloc_guard)
+        .map_err(|error| {
+            log::debug!("buffer rejected: cap={} padded_size={} error=\"{}\"", cap, padded_size, error);
+            error
+        })?;
+    Ok((mem::size_of::<T>(), padded_size, alloc_size))
+}
+
+fn new<'a, T, A: Allocator>(cap: usize, padding: Padding, alloc: A) -> Result<Buffer<'a, T, A>, Error> {
+    let (size, padded_size, alloc_size) = plan::<T>(cap, padding)?;
+    let mut buffer = if cap == 0 {
+        empty(size, padded_size, alloc)
+    } else {
+        buffer_from::<T, A>(cap, size, padded_size, alloc_size, base_align::<T>(padding), alloc)?
+    };
+    buffer.padding = padding;
+    Ok(buffer)
 }
 
 impl <'a, T: 'a> Buffer<'a, T> {
     pub fn new(cap: usize) -> Result<Self, Error> {
-        new(cap, Padding::None)
+        new(cap, Padding::None, Global)
     }
 
-    pub fn dealloc(self) {
-        unsafe {
-            dealloc(self.ptr, self.layout)
-        }
+    /// Remembers which slots hold a value, so `insert` drops the value it replaces and dropping
+    /// the buffer drops every value left in it. This makes types with a destructor, like `String`,
+    /// safe to store. Every slot starts out empty. Reordering methods like `swap` and `rotate_left`
+    /// carry the state along, but `sort`, `iter` and the other readers expect every slot they
+    /// look at to hold a value.
+    pub fn tracked(cap: usize) -> Result<Self, Error> {
+        let mut buffer = Self::new(cap)?;
+        buffer.initialized = Some(::alloc::vec![0; bit_words(cap)]);
+        Ok(buffer)
+    }
+
+    /// Starts a `BufferBuilder`, for combinations of options no single constructor covers
+    pub fn builder() -> BufferBuilder<T> {
+        BufferBuilder::new()
+    }
+
+    /// Same as `new`, named for call sites that want the fallibility spelled out
+    pub fn try_new(cap: usize) -> Result<Self, Error> {
+        Self::new(cap)
     }
 
+    /// Strides entries by `padded_size`, which must be a multiple of `T`'s alignment
     pub fn padded(cap: usize, padded_size: usize) -> Result<Self, Error> {
-        new(cap, Padding::Padded(padded_size))
+        new(cap, Padding::Padded(padded_size), Global)
     }
 
+    /// Strides entries by whole cache lines from a line aligned base, so every slot starts on a line
     pub fn cache_aligned(cap: usize) -> Result<Self, Error> {
-        new(cap, Padding::CacheAligned)
+        new(cap, Padding::CacheAligned, Global)
     }
 
-    pub fn cap(&self) -> usize {
-        self.cap
+    /// Writes the items into a new unpadded buffer of `cap` slots, unfilled slots stay zeroed.
+    /// Fails with `CapacityExceeded` if the iterator yields more than `cap` items.
+    pub fn from_iter_with_cap<I: IntoIterator<Item = T>>(iter: I, cap: usize) -> Result<Self, Error> {
+        let mut buffer = Self::new(cap)?;
+        let mut iter = iter.into_iter();
+        for (index, value) in (0..cap).zip(&mut iter) {
+            unsafe {
+                ptr::write(buffer.entry_ptr_mut(index), value)
+            }
+        }
+        if iter.next().is_some() {
+            return Err(Error::CapacityExceeded);
+        }
+        Ok(buffer)
     }
 
-    pub fn size(&self) -> usize {
-        self.size
+    /// Aligns the base pointer to `align` and rounds each stride up to a multiple of it, so every slot
+    /// is individually aligned. `align` must be a power of two no smaller than `T`'s alignment.
+    pub fn aligned(cap: usize, align: usize) -> Result<Self, Error> {
+        if !align.is_power_of_two() || align < mem::align_of::<T>() {
+            return Err(Error::InvalidAlignment);
+        }
+        let stride = max(mem::size_of::<T>(), 1).checked_add(align - 1)
+            .ok_or(Error::BufferSizeOverflow)? / align * align;
+        let padding = Padding::Padded(stride);
+        let (size, padded_size, alloc_size) = plan::<T>(cap, padding)?;
+        let mut buffer = if cap == 0 {
+            empty(size, padded_size, Global)
+        } else {
+            buffer_aligned(cap, size, padded_size, alloc_size, align)?
+        };
+        buffer.padding = padding;
+        Ok(buffer)
     }
 
-    pub fn padded_size(&self) -> usize {
-        self.padded_size
+    /// Packs `values` into a new buffer, reinterpreting their bytes as entries of `T`.
+    /// Fails when the bytes don't split evenly into entries of `T`.
+    #[cfg(feature = "bytemuck")]
+    pub fn from_pod<U: bytemuck::Pod>(values: &[U]) -> Result<Self, Error>
+    where
+        T: bytemuck::Pod,
+    {
+        let bytes: &[u8] = bytemuck::cast_slice(values);
+        let size = mem::size_of::<T>();
+        if size == 0 || !bytes.len().is_multiple_of(size) {
+            return Err(Error::IncompatibleCast);
+        }
+        let mut buffer = Self::new(bytes.len() / size)?;
+        buffer.data_mut().copy_from_slice(bytes);
+        Ok(buffer)
     }
 
-    pub fn data_size(&self) -> usize {
-        self.cap * self.padded_size
+    /// Reserves `CANARY_LEN` bytes of a known pattern right after the entries, so overruns can be
+    /// caught with `check_canary` where guard pages are unavailable or too heavy.
+    /// `data_size` still reports only the entries.
+    pub fn with_canary(cap: usize) -> Result<Self, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        let len = alloc_size.checked_add(CANARY_LEN)
+            .ok_or(Error::BufferSizeOverflow)
+            .and_then(alloc_guard)?;
+        let layout = Layout::from_size_align(len, mem::align_of::<T>())?;
+        let raw_ptr = Global.allocate_zeroed(layout)
+            .map(|ptr| ptr.cast::<u8>().as_ptr())
+            .unwrap_or(ptr::null_mut());
+        let mut buffer = buffer_at(raw_ptr, layout, cap, size, padded_size, alloc_size, Global)?;
+        buffer.canary = CANARY_LEN;
+        buffer.write_canary();
+        Ok(buffer)
     }
 
-    pub fn entries(&self) -> &Vec<&'a mut T> {
-        &self.entries
+    /// Rebuilds a buffer from the parts returned by `into_raw`, taking back ownership of the allocation.
+    ///
+    /// # Safety
+    ///
+    /// The parts must come from `into_raw` on a heap backed buffer made by the global allocator, and
+    /// must not be used again afterwards. Mapped buffers, e.g. from `huge_page`, cannot be rebuilt.
+    pub unsafe fn from_raw_parts(ptr: *mut u8, cap: usize, size: usize, padded_size: usize, layout: Layout) -> Self {
+        Self::from_raw_parts_in(ptr, cap, size, padded_size, layout, Global)
     }
 
-    pub fn buffers(&self) -> Vec<Vec<u8>> {
-        let mut buffers: Vec<Vec<u8>> = Vec::with_capacity(self.cap);
-        for i in 0..self.cap {
-            buffers.push(
-                unsafe {
-                    Vec::from_raw_parts(self.ptr.add(i * self.padded_size), self.size, self.size)
-                }
-            );
+    /// Builds the buffer for a padding mode chosen at runtime, e.g. from configuration
+    pub fn with_padding(cap: usize, padding: Padding) -> Result<Self, Error> {
+        new(cap, padding, Global)
+    }
+
+    /// Wipes the allocation with `memsec::memzero` before it is released on drop.
+    /// Only the heap allocation is covered, copies made elsewhere are the caller's responsibility.
+    #[cfg(feature = "std")]
+    pub fn secure(cap: usize) -> Result<Self, Error> {
+        let mut buffer = new(cap, Padding::None, Global)?;
+        buffer.secure = true;
+        Ok(buffer)
+    }
+
+    /// Secure buffer that is also locked into RAM, it is wiped and then unlocked on drop
+    #[cfg(feature = "std")]
+    pub fn secure_locked(cap: usize) -> Result<Self, Error> {
+        let mut buffer = Self::secure(cap)?;
+        buffer.lock()?;
+        buffer.locked = true;
+        Ok(buffer)
+    }
+
+    /// Rounds each entry's stride up to the next power of two
+    pub fn pow2(cap: usize) -> Result<Self, Error> {
+        new(cap, Padding::Pow2, Global)
+    }
+
+    /// Skips zeroing the allocation, for callers that overwrite every slot before reading it.
+    /// The bytes must not be read, e.g. through `data()`, until they have been initialized.
+    pub fn uninit(cap: usize) -> Result<Buffer<'a, MaybeUninit<T>>, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        if cap == 0 {
+            return Ok(empty(size, padded_size, Global));
         }
-        buffers
+        let layout = Layout::from_size_align(alloc_size, mem::align_of::<T>())?;
+        let raw_ptr = Global.allocate(layout) // Heap allocation, left uninitialized
+            .map(|ptr| ptr.cast::<u8>().as_ptr())
+            .unwrap_or(ptr::null_mut());
+        buffer_at(raw_ptr, layout, cap, size, padded_size, alloc_size, Global)
     }
 
-    pub fn data(&self) -> Vec<u8> {
-        let data_size = self.data_size();
-        let data: Vec<u8> = unsafe {
-            Vec::from_raw_parts(self.ptr, data_size, data_size)
-        };
-        data
+    /// Backs the buffer with a shared mapping of the file at `path`, so writes persist to disk.
+    /// The file is created if missing and resized to `cap * padded_size`, keeping any existing bytes
+    /// that still fit, so reopening it reads back what was written. It is flushed and unmapped on drop.
+    #[cfg(feature = "mmap")]
+    pub fn mmap_file<P: AsRef<std::path::Path>>(path: P, cap: usize) -> Result<Self, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|_| Error::MapFailed)?;
+        file.set_len(alloc_size as u64)
+            .map_err(|_| Error::MapFailed)?;
+        if cap == 0 {
+            return Ok(empty(size, padded_size, Global));
+        }
+        let mut map = unsafe {
+            memmap2::MmapMut::map_mut(&file)
+        }.map_err(|_| Error::MapFailed)?;
+        let layout = Layout::from_size_align(alloc_size, mem::align_of::<T>())?;
+        // Mappings are page aligned, which covers the alignment of any practical `T`
+        if !(map.as_ptr() as usize).is_multiple_of(layout.align()) {
+            return Err(Error::MapFailed);
+        }
+        let mut buffer = buffer_at(map.as_mut_ptr(), layout, cap, size, padded_size, alloc_size, Global)?;
+        buffer.backing = Backing::File(map);
+        Ok(buffer)
     }
-}
 
-// We need to guarantee the following:
-// * We don't ever allocate `> isize::MAX` byte-size objects
-// * We don't overflow `usize::MAX` and actually allocate too little
-//
-// On 64-bit we just need to check for overflow since trying to allocate
-// `> isize::MAX` bytes will surely fail. On 32-bit and 16-bit we need to add
-// an extra guard for this in case we're running on a platform which can use
-// all 4GB in user-space. e.g. PAE or x32
-#[inline]
-fn alloc_guard(alloc_size: usize) -> Result<usize, Error> {
-    if mem::size_of::<usize>() < 8 && alloc_size > ::core::isize::MAX as usize {
-        Err(Error::AllocCapacityOverflow)
-    } else {
-        Ok(alloc_size)
+    /// Maps the POSIX shared memory object `name`, creating it if missing, so other processes
+    /// mapping the same name see the same slots. `name` follows the `shm_open` rules, e.g. `/ring`.
+    /// The object is grown to `cap * padded_size` when smaller and outlives the buffer until `unlink`.
+    #[cfg(all(feature = "shm", unix))]
+    pub fn shared(name: &str, cap: usize) -> Result<Self, Error> {
+        Self::shared_with(name, cap, libc::O_CREAT)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    extern crate env_logger;
-    use super::*;
+    /// Like `shared`, but fails when an object called `name` already exists
+    #[cfg(all(feature = "shm", unix))]
+    pub fn shared_create(name: &str, cap: usize) -> Result<Self, Error> {
+        Self::shared_with(name, cap, libc::O_CREAT | libc::O_EXCL)
+    }
 
-    #[derive(Debug)]
-    struct Thing {
-        value1: u64,
-        value2: u64,
+    /// Like `shared`, but fails unless an object called `name` already exists
+    #[cfg(all(feature = "shm", unix))]
+    pub fn shared_open(name: &str, cap: usize) -> Result<Self, Error> {
+        Self::shared_with(name, cap, 0)
     }
 
-    #[test]
-    fn should_capture_correct_properties_for_u8() {
-        let buf = Buffer::<u8>::new(1).unwrap();
-        assert_eq!(buf.cap(), 1);
-        assert_eq!(buf.size(), 1);
-        assert_eq!(buf.padded_size(), 1);
+    #[cfg(all(feature = "shm", unix))]
+    fn shared_with(name: &str, cap: usize, flags: libc::c_int) -> Result<Self, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        let name = std::ffi::CString::new(name)
+            .map_err(|_| Error::SharedMemoryFailed)?;
+        let fd = unsafe {
+            libc::shm_open(name.as_ptr(), flags | libc::O_RDWR, 0o600)
+        };
+        if fd < 0 {
+            return Err(Error::SharedMemoryFailed);
+        }
+        // Only ever grow the object, another process may have mapped it at a larger size
+        let sized = unsafe {
+            let mut stat: libc::stat = mem::zeroed();
+            libc::fstat(fd, &mut stat) == 0
+                && (stat.st_size as usize >= alloc_size || libc::ftruncate(fd, alloc_size as libc::off_t) == 0)
+        };
+        let raw_ptr = if sized && cap > 0 {
+            unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    alloc_size,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                )
+            }
+        } else {
+            libc::MAP_FAILED
+        };
+        unsafe {
+            libc::close(fd); // The mapping keeps the object alive
+        }
+        if cap == 0 && sized {
+            return Ok(empty(size, padded_size, Global));
+        }
+        if raw_ptr == libc::MAP_FAILED {
+            return Err(Error::SharedMemoryFailed);
+        }
+        let layout = Layout::from_size_align(alloc_size, mem::align_of::<T>())?;
+        let mut buffer = buffer_at(raw_ptr as *mut u8, layout, cap, size, padded_size, alloc_size, Global)?;
+        buffer.backing = Backing::Shared(name);
+        Ok(buffer)
     }
 
-    #[test]
-    fn should_capture_correct_properties_for_u32() {
-        let buf = Buffer::<u32>::new(1).unwrap();
-        assert_eq!(buf.cap(), 1);
-        assert_eq!(buf.size(), 4);
-        assert_eq!(buf.padded_size(), 4);
+    /// Maps the entries so they end right at an inaccessible guard page, a write past the last slot
+    /// faults immediately instead of corrupting the heap. The mapping is rounded up to whole pages
+    /// plus the guard page, so even a one slot buffer costs two pages. `grow` and `shrink_to` move
+    /// the entries to the heap, dropping the guard.
+    #[cfg(all(feature = "guard-pages", any(target_os = "linux", target_os = "macos")))]
+    pub fn guarded(cap: usize) -> Result<Self, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        if cap == 0 {
+            return Ok(empty(size, padded_size, Global));
+        }
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let data_len = alloc_size.checked_add(page_size - 1)
+            .ok_or(Error::BufferSizeOverflow)? / page_size * page_size;
+        let len = data_len.checked_add(page_size)
+            .ok_or(Error::BufferSizeOverflow)?;
+        let layout = Layout::from_size_align(alloc_size, mem::align_of::<T>())?;
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(Error::InsufficientMemory);
+        }
+        let base = base as *mut u8;
+        let protected = unsafe {
+            libc::mprotect(base.add(data_len) as *mut libc::c_void, page_size, libc::PROT_NONE)
+        };
+        if protected != 0 {
+            unsafe {
+                libc::munmap(base as *mut libc::c_void, len);
+            }
+            return Err(Error::InsufficientMemory);
+        }
+        // Strides are alignment multiples, so the offset that makes the data end on the page keeps it aligned
+        let raw_ptr = unsafe { base.add(data_len - alloc_size) };
+        let mut buffer = buffer_at(raw_ptr, layout, cap, size, padded_size, alloc_size, Global)?;
+        buffer.backing = Backing::Guarded { base, len };
+        Ok(buffer)
     }
 
-    #[test]
-    fn should_capture_correct_properties_for_struct_thing() {
-        let buf = Buffer::<Thing>::new(1).unwrap();
-        assert_eq!(buf.cap(), 1);
-        assert_eq!(buf.size(), 16);
-        assert_eq!(buf.padded_size(), 16);
+    /// Backs the buffer with 2 MiB huge pages, so the base pointer is always 2 MiB aligned.
+    /// Falls back to an equally aligned heap allocation, with a warning, when huge pages are unavailable.
+    #[cfg(target_os = "linux")]
+    pub fn huge_page(cap: usize) -> Result<Self, Error> {
+        let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+        if cap == 0 {
+            return Ok(empty(size, padded_size, Global));
+        }
+        huge_page_from(cap, size, padded_size, alloc_size, max(mem::align_of::<T>(), HUGE_PAGE_SIZE))
     }
 
-    #[test]
-    fn should_capture_correct_properties_for_struct_thing_with_padding() {
-        let buf = Buffer::<Thing>::padded(1, 64).unwrap();
-        assert_eq!(buf.cap(), 1);
-        assert_eq!(buf.size(), 16);
-        assert_eq!(buf.padded_size(), 64);
+    /// Maps the buffer and binds its pages to NUMA `node` before they are first touched
+    #[cfg(feature = "numa")]
+    pub fn on_numa_node(cap: usize, node: u32) -> Result<Self, Error> {
+        #[cfg(target_os = "linux")]
+        {
+            let (size, padded_size, alloc_size) = plan::<T>(cap, Padding::None)?;
+            if cap == 0 {
+                return Ok(empty(size, padded_size, Global));
+            }
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            let align = max(mem::align_of::<T>(), page_size);
+            let len = alloc_size.checked_add(align - 1)
+                .ok_or(Error::BufferSizeOverflow)? / align * align;
+            let layout = Layout::from_size_align(len, align)?;
+            let raw_ptr = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if raw_ptr == libc::MAP_FAILED {
+                return Err(Error::InsufficientMemory);
+            }
+            let mut nodemask: Vec<libc::c_ulong> = Vec::new();
+            let bits = mem::size_of::<libc::c_ulong>() * 8;
+            nodemask.resize(node as usize / bits + 1, 0);
+            nodemask[node as usize / bits] |= 1 << (node as usize % bits);
+            let bound = unsafe {
+                libc::syscall(
+                    libc::SYS_mbind,
+                    raw_ptr,
+                    len,
+                    MPOL_BIND,
+                    nodemask.as_ptr(),
+                    nodemask.len() * bits + 1,
+                    MPOL_MF_STRICT,
+                )
+            };
+            if bound != 0 {
+                unsafe {
+                    libc::munmap(raw_ptr, len);
+                }
+                return Err(Error::NumaUnavailable);
+            }
+            let mut buffer = buffer_at(raw_ptr as *mut u8, layout, cap, size, padded_size, alloc_size, Global)?;
+            buffer.backing = Backing::Mapped;
+            Ok(buffer)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (cap, node);
+            Err(Error::NumaUnavailable)
+        }
     }
+}
 
-    #[test]
-    fn should_expand_buffer_entries_in_memory_but_not_views() {
-        let buf = Buffer::<Thing>::padded(1, 64).unwrap();
-        let buffers = buf.buffers();
-        assert_eq!(buffers[0].len(), 16);
+impl <'a, T: 'a, A: Allocator> Buffer<'a, T, A> {
+    /// Unpadded buffer whose allocation is made and released through `alloc`
+    pub fn new_in(cap: usize, alloc: A) -> Result<Self, Error> {
+        new(cap, Padding::None, alloc)
     }
 
-    #[test]
-    fn should_calculate_data_size_to_match_underying_buffer_size_for_unpadded() {
-        let buf = Buffer::<u8>::new(1).unwrap();
-        let data = buf.data();
-        assert_eq!(data.len(), buf.data_size());
+    /// Releases the allocation now rather than at the end of scope
+    pub fn dealloc(self) {
+        drop(self)
     }
 
-    #[test]
-    fn should_place_updated_data_propertly_in_second_slot() {
-        let mut buf = Buffer::<u8>::new(2).unwrap();
-        *buf.entries[1] = 12;
-        assert_eq!(vec![0, 12], buf.data());
+    /// Like `from_raw_parts`, for parts of a buffer whose allocation was made by `alloc`
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_raw_parts`, with `alloc` standing in for the global allocator.
+    pub unsafe fn from_raw_parts_in(ptr: *mut u8, cap: usize, size: usize, padded_size: usize, layout: Layout, alloc: A) -> Self {
+        let mut buffer = match buffer_at(ptr, layout, cap, size, padded_size, cap * padded_size, alloc) {
+            Ok(buffer) => buffer,
+            Err(_) => panic!("from_raw_parts requires a non null pointer"),
+        };
+        if layout.size() == 0 {
+            buffer.backing = Backing::Empty;
+        }
+        buffer
     }
 
-    #[test]
-    fn should_update_struct_in_data_properly() {
-        let mut buf = Buffer::<Thing>::new(2).unwrap();
-        buf.entries[0].value2 = 36;
-        buf.entries[1].value1 = 12;
-        assert_eq!(vec![0,0,0,0,0,0,0,0, 36,0,0,0,0,0,0,0, 12,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0], buf.data());
+    /// Gives up ownership of the allocation, returning `(ptr, cap, size, padded_size, layout)` for
+    /// `from_raw_parts`. Nothing is freed or wiped, the caller becomes responsible for the allocation.
+    pub fn into_raw(self) -> (*mut u8, usize, usize, usize, Layout) {
+        let this = ManuallyDrop::new(self);
+        drop(unsafe { ptr::read(&this.initialized) });
+        drop(unsafe { ptr::read(&this.occupancy) });
+        #[cfg(feature = "metrics")]
+        {
+            if this.layout.size() > 0 {
+                metrics::record_release(this.layout.size());
+            }
+        }
+        (this.ptr, this.cap, this.size, this.padded_size, this.layout)
     }
 
-    #[test]
-    fn should_place_updated_data_propertly_in_second_slot_with_padding() {
-        let mut buf = Buffer::<u8>::padded(2, 4).unwrap();
-        *buf.entries[1] = 12;
-        assert_eq!(vec![0,0,0,0, 12,0,0,0], buf.data());
+    /// Moves the entries into a larger heap allocation with the same stride, the new slots are zeroed.
+    /// Raw pointers taken from the buffer before growing are left dangling.
+    pub fn grow(&mut self, new_cap: usize) -> Result<(), Error> {
+        if new_cap < self.cap {
+            return Err(Error::CapacityTooSmall);
+        }
+        self.reallocate(new_cap)
     }
 
-    #[test]
-    fn should_update_struct_in_data_properly_with_padding() {
-        let mut buf = Buffer::<Thing>::padded(2, 18).unwrap();
-        buf.entries[0].value2 = 36;
-        buf.entries[1].value1 = 12;
-        assert_eq!(vec![0,0,0,0,0,0,0,0, 36,0,0,0,0,0,0,0, 0,0, 12,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0 ,0,0], buf.data());
+    /// Moves the first `new_cap` entries into a smaller heap allocation, a no-op unless `new_cap < cap`.
+    /// Raw pointers taken from the buffer before shrinking are left dangling.
+    pub fn shrink_to(&mut self, new_cap: usize) -> Result<(), Error> {
+        if new_cap >= self.cap {
+            return Ok(());
+        }
+        self.reallocate(new_cap)
+    }
+
+    /// Moves slots `at..cap` into a new buffer with the same stride, like `Vec::split_off`, and shrinks
+    /// this one to `at` slots. Both halves own their allocations and release them independently.
+    /// The tail of a locked buffer is locked too, failing with `LockFailed` when it can't be.
+    pub fn split_off(&mut self, at: usize) -> Result<Self, Error> where A: Clone {
+        if at > self.cap {
+            return Err(Error::IndexOutOfBounds);
+        }
+        let (size, padded_size) = (self.size, self.padded_size);
+        let tail_cap = self.cap - at;
+        let mut tail = if tail_cap == 0 {
+            empty(size, padded_size, self.alloc.clone())
+        } else {
+            buffer_from(tail_cap, size, padded_size, tail_cap * padded_size, self.layout.align(), self.alloc.clone())?
+        };
+        #[cfg(feature = "std")]
+        {
+            // Locked before the copy so the moved bytes never sit in swappable memory
+            tail.secure = self.secure;
+            if self.locked && tail_cap > 0 {
+                tail.lock()?;
+                tail.locked = true;
+            }
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr.add(slot_offset(at, padded_size)), tail.ptr, tail.data_size)
+        }
+        tail.padding = self.padding;
+        tail.len = self.len.saturating_sub(at);
+        let split_bits = |bits: &[u64]| {
+            let mut tail_bits = ::alloc::vec![0; bit_words(tail_cap)];
+            for i in at..at + tail_cap {
+                set_bit(&mut tail_bits, i - at, get_bit(bits, i));
+            }
+            tail_bits
+        };
+        tail.initialized = self.initialized.as_ref().map(|bits| split_bits(bits));
+        if !self.occupancy.is_empty() {
+            tail.occupancy = split_bits(&self.occupancy);
+        }
+        if tail_cap == 0 {
+            return Ok(tail);
+        }
+        // The moved values belong to `tail` now, so shrinking must not drop them. Their bits are
+        // cleared rather than truncated, relayout reads them up to the old `cap` before resizing.
+        let initialized = self.initialized.clone();
+        if let Some(bits) = self.initialized.as_mut() {
+            for i in at..at + tail_cap {
+                set_bit(bits, i, false);
+            }
+        }
+        // relayout fails before anything is moved or freed, so the values are still this buffer's
+        let align = self.layout.align();
+        if let Err(error) = self.relayout(at, padded_size, align) {
+            self.initialized = initialized;
+            tail.initialized = None;
+            return Err(error);
+        }
+        Ok(tail)
+    }
+
+    /// Moves the entries into a new heap allocation strided for `padding`, e.g. to promote a packed
+    /// buffer to `CacheAligned` once false sharing shows up. `cap` and every entry are kept.
+    /// Raw pointers taken from the buffer before repadding are left dangling.
+    pub fn repad(&mut self, padding: Padding) -> Result<(), Error> {
+        let (_, padded_size, _) = plan::<T>(self.cap, padding)?;
+        let cap = self.cap;
+        let align = max(self.layout.align(), base_align::<T>(padding));
+        let result = self.relayout(cap, padded_size, align);
+        if self.padded_size == padded_size {
+            self.padding = padding;
+        }
+        result
     }
 
-    // test cache aligned does correct padding
-    // test cache aligned with natural align doesn't over pad
+    // Swaps the allocation for a zeroed heap block of `cap` slots, keeping as many leading bytes as fit
+    fn reallocate(&mut self, cap: usize) -> Result<(), Error> {
+        let padded_size = self.padded_size;
+        let align = self.layout.align();
+        self.relayout(cap, padded_size, align)
+    }
+
+    // Like `reallocate`, restriding to `padded_size` from a base aligned to `align`. Only the `size` bytes of each entry are carried
+    // over when the stride changes, otherwise the leading bytes are copied as is, padding included.
+    // Errors, `LockFailed` included, leave the buffer untouched.
+    fn relayout(&mut self, cap: usize, padded_size: usize, align: usize) -> Result<(), Error> {
+        let data_size = cap.checked_mul(padded_size)
+            .ok_or(Error::BufferSizeOverflow)
+            .and_then(alloc_guard)?;
+        let len = data_size.checked_add(self.canary)
+            .ok_or(Error::BufferSizeOverflow)?;
+        let layout = Layout::from_size_align(len, align)?;
+        let (ptr, backing) = if layout.size() == 0 {
+            (align as *mut u8, Backing::Empty) // Dangling but aligned, like `empty`
+        } else {
+            let raw_ptr = self.alloc.allocate_zeroed(layout)
+                .map(|ptr| ptr.cast::<u8>().as_ptr())
+                .map_err(|_| {
+                    log::debug!("buffer allocation failed: alloc_size={} align={}", layout.size(), layout.align());
+                    Error::InsufficientMemory
+                })?;
+            // Locked before anything is moved, so failing leaves the buffer as it was
+            #[cfg(feature = "std")]
+            {
+                if self.locked && !lock_range(raw_ptr, data_size) {
+                    unsafe {
+                        self.alloc.deallocate(NonNull::new_unchecked(raw_ptr), layout)
+                    }
+                    return Err(Error::LockFailed);
+                }
+            }
+            #[cfg(feature = "metrics")]
+            metrics::record_alloc(layout.size());
+            log::debug!("buffer allocated: cap={} size={} padded_size={} alloc_size={} align={}",
+                cap, self.size, padded_size, layout.size(), layout.align());
+            (raw_ptr, Backing::Heap)
+        };
+        unsafe {
+            if padded_size == self.padded_size {
+                ptr::copy_nonoverlapping(self.ptr, ptr, min(self.data_size, data_size));
+            } else {
+                for i in 0..min(self.cap, cap) {
+                    ptr::copy_nonoverlapping(
+                        self.ptr.add(slot_offset(i, self.padded_size)),
+                        ptr.add(slot_offset(i, padded_size)),
+                        self.size,
+                    );
+                }
+            }
+        }
+        if let Some(mut bits) = self.initialized.take() {
+            for i in cap..self.cap {
+                if get_bit(&bits, i) {
+                    unsafe {
+                        ptr::drop_in_place(self.entry_ptr_mut(i))
+                    }
+                }
+            }
+            resize_bits(&mut bits, cap);
+            self.initialized = Some(bits);
+        }
+        if !self.occupancy.is_empty() {
+            resize_bits(&mut self.occupancy, cap);
+        }
+        self.release();
+        self.ptr = ptr;
+        self.layout = layout;
+        self.backing = backing;
+        self.cap = cap;
+        self.len = min(self.len, cap);
+        self.padded_size = padded_size;
+        self.data_size = data_size;
+        self.write_canary();
+        Ok(())
+    }
+
+    /// Reinterprets the allocation as entries of `U`, packed back to back over the whole region
+    /// (padding included) so the capacity becomes `data_size / size_of::<U>()`. Fails, releasing
+    /// the buffer, when `U` needs a stricter alignment or doesn't evenly divide the stride.
+    ///
+    /// # Safety
+    ///
+    /// Every resulting slot must hold bytes that are a valid `U`.
+    pub unsafe fn cast<U: 'a>(self) -> Result<Buffer<'a, U, A>, Error> {
+        let size = mem::size_of::<U>();
+        let align = mem::align_of::<U>();
+        if size == 0 || !self.padded_size.is_multiple_of(size) {
+            return Err(Error::IncompatibleCast);
+        }
+        if align > self.layout.align() || !(self.ptr as usize).is_multiple_of(align) {
+            return Err(Error::IncompatibleCast);
+        }
+        let cap = self.data_size / size;
+        Ok(self.retype(cap, size, size))
+    }
+
+    // Hands the allocation over to a buffer of another entry type without releasing it
+    unsafe fn retype<U: 'a>(self, cap: usize, size: usize, padded_size: usize) -> Buffer<'a, U, A> {
+        let this = ManuallyDrop::new(self);
+        drop(ptr::read(&this.initialized));
+        drop(ptr::read(&this.occupancy));
+        Buffer {
+            alloc: ptr::read(&this.alloc),
+            layout: this.layout,
+            ptr: this.ptr,
+            cap,
+            size,
+            padded_size,
+            data_size: this.data_size,
+            #[cfg(feature = "std")]
+            secure: this.secure,
+            #[cfg(feature = "std")]
+            locked: this.locked,
+            padding: Padding::None,
+            canary: this.canary,
+            len: 0,
+            initialized: None,
+            occupancy: Vec::new(),
+            backing: ptr::read(&this.backing),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Removes the shared memory object's name, existing mappings stay valid until they are dropped
+    #[cfg(all(feature = "shm", unix))]
+    pub fn unlink(&self) -> Result<(), Error> {
+        match self.backing {
+            Backing::Shared(ref name) if unsafe { libc::shm_unlink(name.as_ptr()) } == 0 => Ok(()),
+            _ => Err(Error::SharedMemoryFailed),
+        }
+    }
+
+    // Wipes, unlocks and frees the allocation the same way it was made
+    fn release(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            if self.secure {
+                self.wipe_bytes();
+            }
+            if self.locked {
+                let _ = self.unlock();
+            }
+        }
+        // Same condition `buffer_at` counts allocations under, zero sized heap layouts included
+        if self.layout.size() > 0 {
+            #[cfg(feature = "metrics")]
+            metrics::record_release(self.layout.size());
+            log::debug!("buffer released: cap={} alloc_size={}", self.cap, self.layout.size());
+        }
+        match mem::replace(&mut self.backing, Backing::Empty) {
+            Backing::Empty => {},
+            Backing::Heap => unsafe {
+                self.alloc.deallocate(NonNull::new_unchecked(self.ptr), self.layout)
+            },
+            #[cfg(target_os = "linux")]
+            Backing::Mapped => unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.layout.size());
+            },
+            #[cfg(feature = "mmap")]
+            Backing::File(map) => {
+                let _ = map.flush(); // Dropping the map unmaps it
+            },
+            #[cfg(all(feature = "shm", unix))]
+            Backing::Shared(_) => unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.layout.size());
+            },
+            #[cfg(all(feature = "guard-pages", any(target_os = "linux", target_os = "macos")))]
+            Backing::Guarded { base, len } => unsafe {
+                libc::munmap(base as *mut libc::c_void, len);
+            },
+            #[cfg(unix)]
+            Backing::Memalign => unsafe {
+                libc::free(self.ptr as *mut libc::c_void);
+            },
+            #[cfg(windows)]
+            Backing::Memalign => unsafe {
+                _aligned_free(self.ptr as *mut core::ffi::c_void);
+            },
+        }
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn padded_size(&self) -> usize {
+        self.padded_size
+    }
+
+    /// Alignment guaranteed for the base pointer of the allocation
+    pub fn alignment(&self) -> usize {
+        self.layout.align()
+    }
+
+    /// Whether every slot starts on a cache line, so both the stride and the base pointer are line multiples
+    pub fn is_cache_aligned(&self) -> bool {
+        let line = cache_line_size();
+        self.padded_size.is_multiple_of(line) && (self.ptr as usize).is_multiple_of(line)
+    }
+
+    /// Mode the stride was derived from, buffers built without one, or cast, report `Padding::None`
+    pub fn padding_mode(&self) -> Padding {
+        self.padding
+    }
+
+    /// Size in bytes of the allocation, checked against overflow when the buffer was built
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    /// References to every slot in order, collected on demand so constructing a buffer only ever
+    /// allocates its data region. Prefer `iter` or indexing where a `Vec` isn't needed.
+    pub fn entries(&self) -> Vec<&T> {
+        self.iter().collect()
+    }
+
+    /// Mutable references to every slot in order. They borrow the buffer, so none of them can
+    /// outlive it or be used once it has been reallocated.
+    pub fn entries_mut(&mut self) -> Vec<&mut T> {
+        self.iter_mut().collect()
+    }
+
+    /// Base pointer of the allocation, typed entries are `padded_size` bytes apart rather than `size`
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr as *const T
+    }
+
+    /// Mutable base pointer of the allocation, typed entries are `padded_size` bytes apart rather than `size`
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr as *mut T
+    }
+
+    pub fn as_byte_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    /// Offset in bytes of a slot from the base pointer, `index` must be below `cap`.
+    /// Debug builds panic otherwise.
+    pub fn byte_offset(&self, index: usize) -> usize {
+        debug_assert!(index < self.cap, "index out of bounds: the cap is {} but the index is {}", self.cap, index);
+        slot_offset(index, self.padded_size)
+    }
+
+    /// Address of a slot, `index` must be below `cap` as for `byte_offset`
+    pub fn entry_ptr(&self, index: usize) -> *const T {
+        self.ptr.wrapping_add(self.byte_offset(index)) as *const T
+    }
+
+    pub fn entry_ptr_mut(&mut self, index: usize) -> *mut T {
+        self.ptr.wrapping_add(self.byte_offset(index)) as *mut T
+    }
+
+    /// Tells the kernel how the allocation's pages will be used, e.g. `DontNeed` lets an idle pooled
+    /// buffer give its physical pages back while keeping the mapping. The base pointer must be page
+    /// aligned, as with `huge_page` or `aligned(cap, 4096)`, or `AdviseFailed` is returned.
+    ///
+    /// Takes `&mut self` rather than `&self` because after `DontNeed` on Linux private pages read back
+    /// as zero, which changes the entries under any outstanding shared borrow.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn advise(&mut self, advice: Advice) -> Result<(), Error> {
+        if self.data_size == 0 {
+            return Ok(());
+        }
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        if !(self.ptr as usize).is_multiple_of(page_size) {
+            return Err(Error::AdviseFailed);
+        }
+        // Only whole pages may be dropped, the kernel would otherwise round into whatever follows
+        let len = match advice {
+            Advice::DontNeed => self.data_size / page_size * page_size,
+            _ => self.data_size,
+        };
+        let advice = match advice {
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Random => libc::MADV_RANDOM,
+        };
+        if len == 0 {
+            return Ok(());
+        }
+        match unsafe { libc::madvise(self.ptr as *mut libc::c_void, len, advice) } {
+            0 => Ok(()),
+            _ => Err(Error::AdviseFailed),
+        }
+    }
+
+    /// Hints the CPU to start loading the cache line of slot `index`, without touching its contents.
+    /// A no-op when `index` is out of bounds or the target has no prefetch instruction.
+    #[inline]
+    pub fn prefetch(&self, index: usize, locality: Locality) {
+        if index >= self.cap {
+            return;
+        }
+        let ptr = self.entry_ptr(index) as *const u8;
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            use core::arch::x86_64::{ _mm_prefetch, _MM_HINT_NTA, _MM_HINT_T0, _MM_HINT_T1, _MM_HINT_T2 };
+            let ptr = ptr as *const i8;
+            match locality {
+                Locality::T0 => _mm_prefetch::<_MM_HINT_T0>(ptr),
+                Locality::T1 => _mm_prefetch::<_MM_HINT_T1>(ptr),
+                Locality::T2 => _mm_prefetch::<_MM_HINT_T2>(ptr),
+                Locality::Nta => _mm_prefetch::<_MM_HINT_NTA>(ptr),
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            use core::arch::asm;
+            match locality {
+                Locality::T0 => asm!("prfm pldl1keep, [{0}]", in(reg) ptr, options(nostack, readonly, preserves_flags)),
+                Locality::T1 => asm!("prfm pldl2keep, [{0}]", in(reg) ptr, options(nostack, readonly, preserves_flags)),
+                Locality::T2 => asm!("prfm pldl3keep, [{0}]", in(reg) ptr, options(nostack, readonly, preserves_flags)),
+                Locality::Nta => asm!("prfm pldl1strm, [{0}]", in(reg) ptr, options(nostack, readonly, preserves_flags)),
+            }
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            let _ = (ptr, locality);
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.cap {
+            return None;
+        }
+        Some(unsafe {
+            &*self.entry_ptr(index)
+        })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.cap {
+            return None;
+        }
+        Some(unsafe {
+            &mut *self.entry_ptr_mut(index)
+        })
+    }
+
+    /// Resets every byte of the allocation, padding included, back to zero.
+    /// Values of a tracked buffer are dropped first.
+    pub fn zero(&mut self) {
+        self.drop_initialized();
+        unsafe {
+            ptr::write_bytes(self.ptr, 0, self.data_size())
+        }
+    }
+
+    /// Slots filled through `push`, counting from the first. Constructors start every buffer at 0.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.cap
+    }
+
+    /// Writes `value` into the slot after the last pushed one, handing it back when every slot is in use
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        let index = self.len;
+        self.insert(index, value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Moves the last pushed value out, its slot keeps the bytes but is no longer counted
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        if let Some(bits) = self.initialized.as_mut() {
+            set_bit(bits, self.len, false);
+        }
+        Some(unsafe {
+            ptr::read(self.entry_ptr(self.len))
+        })
+    }
+
+    /// Marks slot `index` as live or free for sparse, arena style use. The marks are kept apart from
+    /// the entries, which are never touched, and the bitmap is only allocated once a slot is marked.
+    pub fn set_occupied(&mut self, index: usize, occupied: bool) {
+        assert!(index < self.cap, "index out of bounds: the cap is {} but the index is {}", self.cap, index);
+        if self.occupancy.is_empty() {
+            if !occupied {
+                return;
+            }
+            self.occupancy.resize(bit_words(self.cap), 0);
+        }
+        set_bit(&mut self.occupancy, index, occupied);
+    }
+
+    /// Whether slot `index` was marked with `set_occupied`, out of bounds slots never are
+    pub fn is_occupied(&self, index: usize) -> bool {
+        index < self.cap && !self.occupancy.is_empty() && get_bit(&self.occupancy, index)
+    }
+
+    /// Indices of the occupied slots in ascending order, skipping whole words of free slots at a time
+    pub fn occupied_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.occupancy.iter()
+            .enumerate()
+            .flat_map(|(word, &bits)| {
+                let mut bits = bits;
+                core::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(word * 64 + bit)
+                })
+            })
+    }
+
+    /// Whether this buffer was built with `tracked`
+    pub fn is_tracked(&self) -> bool {
+        self.initialized.is_some()
+    }
+
+    /// Moves `value` into slot `index`. A tracked buffer drops the value the slot held,
+    /// otherwise the previous contents are overwritten without being dropped.
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index < self.cap, "index out of bounds: the cap is {} but the index is {}", self.cap, index);
+        let slot = self.entry_ptr_mut(index);
+        if let Some(bits) = self.initialized.as_mut() {
+            if get_bit(bits, index) {
+                unsafe {
+                    ptr::drop_in_place(slot)
+                }
+            }
+            set_bit(bits, index, true);
+        }
+        unsafe {
+            ptr::write(slot, value)
+        }
+    }
+
+    /// Moves the value out of slot `index` of a tracked buffer, leaving the slot empty.
+    /// Untracked buffers can't tell whether a slot holds a value, so they always return `None`.
+    pub fn take(&mut self, index: usize) -> Option<T> {
+        let bits = self.initialized.as_mut()?;
+        if index >= self.cap || !get_bit(bits, index) {
+            return None;
+        }
+        set_bit(bits, index, false);
+        Some(unsafe {
+            ptr::read(self.entry_ptr(index))
+        })
+    }
+
+    // Runs the destructor of every value a tracked buffer holds and marks the slots empty
+    fn drop_initialized(&mut self) {
+        let bits = match self.initialized.as_mut() {
+            Some(bits) => bits,
+            None => return,
+        };
+        for index in 0..self.cap {
+            if get_bit(bits, index) {
+                set_bit(bits, index, false);
+                unsafe {
+                    ptr::drop_in_place(self.ptr.add(slot_offset(index, self.padded_size)) as *mut T)
+                }
+            }
+        }
+    }
+
+    /// Forgets every pushed value without dropping or zeroing them
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Zeroes the `padded_size - size` bytes after each entry, leaving the entries untouched,
+    /// so stale padding doesn't leak out with `data()`. A no-op for packed buffers.
+    pub fn zero_padding(&mut self) {
+        let (size, padded_size) = (self.size, self.padded_size);
+        if padded_size == size {
+            return;
+        }
+        for slot in self.data_mut().chunks_mut(padded_size) {
+            for byte in &mut slot[size..] {
+                *byte = 0;
+            }
+        }
+    }
+
+    /// Sets every byte of the allocation, padding included, e.g. to poison it with `0xCC` in tests.
+    /// Values of a tracked buffer are dropped first.
+    pub fn fill_bytes(&mut self, byte: u8) {
+        self.drop_initialized();
+        unsafe {
+            ptr::write_bytes(self.ptr, byte, self.data_size())
+        }
+    }
+
+    /// Writes a clone of `value` into every slot through `insert`
+    pub fn fill(&mut self, value: T) where T: Clone {
+        for i in 0..self.cap {
+            self.insert(i, value.clone());
+        }
+    }
+
+    /// Writes `f(i)` into slot `i` through `insert`
+    pub fn fill_with<F: FnMut(usize) -> T>(&mut self, mut f: F) {
+        for i in 0..self.cap {
+            self.insert(i, f(i));
+        }
+    }
+
+    /// Copies `src` into the leading slots, one entry at a time so the padded stride is honored
+    pub fn copy_from_slice(&mut self, src: &[T]) -> Result<(), Error> where T: Copy {
+        if src.len() > self.cap {
+            return Err(Error::CapacityExceeded);
+        }
+        if self.padded_size == self.size {
+            unsafe {
+                ptr::copy_nonoverlapping(src.as_ptr(), self.ptr as *mut T, src.len())
+            }
+            return Ok(());
+        }
+        for (i, value) in src.iter().enumerate() {
+            unsafe {
+                ptr::write(self.entry_ptr_mut(i), *value)
+            }
+        }
+        Ok(())
+    }
+
+    /// Byte copies `src`, padding included, in one pass when both buffers share `cap` and `padded_size`
+    pub fn copy_from<'b, B: Allocator>(&mut self, src: &Buffer<'b, T, B>) -> Result<(), Error> where T: Copy {
+        if self.cap != src.cap || self.padded_size != src.padded_size {
+            return Err(Error::LayoutMismatch);
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(src.ptr, self.ptr, self.data_size)
+        }
+        Ok(())
+    }
+
+    /// Whether the canary after the entries is intact, always true for buffers built without one
+    pub fn check_canary(&self) -> bool {
+        let canary = unsafe {
+            slice::from_raw_parts(self.ptr.add(self.data_size), self.canary)
+        };
+        canary.iter().all(|&byte| byte == CANARY)
+    }
+
+    fn write_canary(&mut self) {
+        unsafe {
+            ptr::write_bytes(self.ptr.add(self.data_size), CANARY, self.canary)
+        }
+    }
+
+    /// Exchanges the `size` bytes of two slots, padding bytes stay with their slot
+    pub fn swap(&mut self, i: usize, j: usize) {
+        assert!(i < self.cap, "index out of bounds: the cap is {} but the index is {}", self.cap, i);
+        assert!(j < self.cap, "index out of bounds: the cap is {} but the index is {}", self.cap, j);
+        if i == j {
+            return;
+        }
+        unsafe {
+            ptr::swap_nonoverlapping(self.entry_ptr_mut(i) as *mut u8, self.entry_ptr_mut(j) as *mut u8, self.size)
+        }
+        if let Some(bits) = self.initialized.as_mut() {
+            let (a, b) = (get_bit(bits, i), get_bit(bits, j));
+            set_bit(bits, i, b);
+            set_bit(bits, j, a);
+        }
+    }
+
+    /// Searches the entries for `target`, mirroring `slice::binary_search`: `Ok` holds a matching index
+    /// and `Err` the index `target` could be inserted at. The entries must already be sorted.
+    pub fn binary_search(&self, target: &T) -> Result<usize, usize> where T: Ord {
+        self.binary_search_by(|entry| entry.cmp(target))
+    }
+
+    /// Like `binary_search`, with `f` reporting how each entry orders against the target
+    pub fn binary_search_by<F: FnMut(&T) -> Ordering>(&self, mut f: F) -> Result<usize, usize> {
+        let (mut low, mut high) = (0, self.cap);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self[mid]) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Sorts the entries in place, equal entries may be reordered
+    pub fn sort(&mut self) where T: Ord {
+        self.sort_by(|a, b| a.cmp(b))
+    }
+
+    /// Sorts the entries in place by `compare`, equal entries may be reordered.
+    /// Padded entries are heap sorted by swapping their `size` bytes, so padding never moves.
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut compare: F) {
+        if let Some(entries) = self.as_mut_slice() {
+            return entries.sort_unstable_by(compare);
+        }
+        let cap = self.cap;
+        for root in (0..cap / 2).rev() {
+            self.sift_down(root, cap, &mut compare);
+        }
+        for end in (1..cap).rev() {
+            self.swap(0, end);
+            self.sift_down(0, end, &mut compare);
+        }
+    }
+
+    // Moves the entry at `root` down the max heap held in `0..end` until both children order before it
+    fn sift_down<F: FnMut(&T, &T) -> Ordering>(&mut self, mut root: usize, end: usize, compare: &mut F) {
+        loop {
+            let mut child = 2 * root + 1;
+            if child >= end {
+                return;
+            }
+            if child + 1 < end && compare(&self[child], &self[child + 1]) == Ordering::Less {
+                child += 1;
+            }
+            if compare(&self[root], &self[child]) != Ordering::Less {
+                return;
+            }
+            self.swap(root, child);
+            root = child;
+        }
+    }
+
+    /// Reverses the order of the entries, only their `size` bytes move so padding stays in place
+    pub fn reverse(&mut self) {
+        let cap = self.cap;
+        self.reverse_range(0, cap);
+    }
+
+    /// Moves every entry `n` slots towards the front, wrapping the first `n % cap` round to the back
+    pub fn rotate_left(&mut self, n: usize) {
+        if self.cap == 0 {
+            return;
+        }
+        let n = n % self.cap;
+        // Three reversals rotate in place without a second allocation
+        self.reverse_range(0, n);
+        self.reverse_range(n, self.cap);
+        self.reverse();
+    }
+
+    /// Moves every entry `n` slots towards the back, wrapping the last `n % cap` round to the front
+    pub fn rotate_right(&mut self, n: usize) {
+        if self.cap == 0 {
+            return;
+        }
+        let n = n % self.cap;
+        self.rotate_left(self.cap - n);
+    }
+
+    // Reverses the entries in `start..end` by swapping their `size` bytes
+    fn reverse_range(&mut self, start: usize, end: usize) {
+        let (mut i, mut j) = (start, end);
+        while i + 1 < j {
+            j -= 1;
+            self.swap(i, j);
+            i += 1;
+        }
+    }
+
+    /// Zeroes the allocation in a way the optimizer cannot elide, values of a tracked buffer
+    /// are dropped first
+    #[cfg(feature = "std")]
+    pub fn wipe(&mut self) {
+        self.drop_initialized();
+        self.wipe_bytes();
+    }
+
+    #[cfg(feature = "std")]
+    fn wipe_bytes(&mut self) {
+        unsafe {
+            memsec::memzero(self.ptr, self.data_size())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Locks the allocation into RAM so it is never written to swap
+    #[cfg(feature = "std")]
+    pub fn lock(&self) -> Result<(), Error> {
+        if lock_range(self.ptr, self.data_size()) {
+            Ok(())
+        } else {
+            Err(Error::LockFailed)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn unlock(&self) -> Result<(), Error> {
+        if unsafe { memsec::munlock(self.ptr, self.data_size()) } {
+            Ok(())
+        } else {
+            Err(Error::LockFailed)
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Views the entries as a typed slice, only possible when there is no padding between them
+    pub fn as_slice(&self) -> Option<&[T]> {
+        if self.padded_size != self.size {
+            return None;
+        }
+        Some(unsafe {
+            slice::from_raw_parts(self.ptr as *const T, self.cap)
+        })
+    }
+
+    /// Reinterprets the packed entries as a slice of the `Pod` type `U`, checked by `bytemuck`.
+    /// Fails when there is padding between entries, or when the base pointer or region length
+    /// don't suit `U`.
+    #[cfg(feature = "bytemuck")]
+    pub fn as_pod_slice<U: bytemuck::Pod>(&self) -> Result<&[U], Error> {
+        if self.padded_size != self.size {
+            return Err(Error::IncompatibleCast);
+        }
+        bytemuck::try_cast_slice(self.data())
+            .map_err(|_| Error::IncompatibleCast)
+    }
+
+    pub fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        if self.padded_size != self.size {
+            return None;
+        }
+        Some(unsafe {
+            slice::from_raw_parts_mut(self.ptr as *mut T, self.cap)
+        })
+    }
+
+    /// Hands the contiguous storage out as uninitialized slots, for APIs that fill memory themselves.
+    /// Values written through it are never dropped, even by a tracked buffer, and storing
+    /// `MaybeUninit::uninit()` leaves bytes that `data()` and the entry accessors must not read.
+    pub fn as_uninit_slice_mut(&mut self) -> Option<&mut [MaybeUninit<T>]> {
+        if self.padded_size != self.size {
+            return None;
+        }
+        Some(unsafe {
+            slice::from_raw_parts_mut(self.ptr as *mut MaybeUninit<T>, self.cap)
+        })
+    }
+
+    /// Groups of `n` entries, the last may be shorter. Only available for contiguous (unpadded) entries.
+    ///
+    /// Panics if `n` is 0, padded or not.
+    pub fn chunks(&self, n: usize) -> Option<slice::Chunks<'_, T>> {
+        assert!(n > 0, "chunk size must be non zero");
+        self.as_slice().map(|entries| entries.chunks(n))
+    }
+
+    /// Mutable groups of `n` entries, as for `chunks`.
+    ///
+    /// Panics if `n` is 0, padded or not.
+    pub fn chunks_mut(&mut self, n: usize) -> Option<slice::ChunksMut<'_, T>> {
+        assert!(n > 0, "chunk size must be non zero");
+        self.as_mut_slice().map(|entries| entries.chunks_mut(n))
+    }
+
+    /// Splits contiguous (unpadded) entries into the disjoint slots `0..mid` and `mid..cap`.
+    ///
+    /// Panics if `mid > cap`.
+    pub fn split_at_mut(&mut self, mid: usize) -> Option<(&mut [T], &mut [T])> {
+        assert!(mid <= self.cap, "mid is out of bounds: the cap is {} but mid is {}", self.cap, mid);
+        self.as_mut_slice().map(|entries| entries.split_at_mut(mid))
+    }
+
+    /// Splits into disjoint views of `0..mid` and `mid..cap` that honor the padded stride.
+    ///
+    /// Panics if `mid > cap`.
+    pub fn split_views_mut(&mut self, mid: usize) -> (BufferViewMut<'_, T>, BufferViewMut<'_, T>) {
+        assert!(mid <= self.cap, "mid is out of bounds: the cap is {} but mid is {}", self.cap, mid);
+        let right = self.ptr.wrapping_add(mid * self.padded_size);
+        unsafe {
+            (
+                view::view_mut(self.ptr, mid, self.size, self.padded_size),
+                view::view_mut(right, self.cap - mid, self.size, self.padded_size),
+            )
+        }
+    }
+
+    /// Views the slots in `range` as their own buffer, index 0 of the window is slot `range.start`.
+    ///
+    /// Panics if the range is decreasing or ends past `cap`.
+    pub fn window(&self, range: Range<usize>) -> BufferView<'_, T> {
+        self.check_window(&range);
+        unsafe {
+            view::view(self.ptr.wrapping_add(range.start * self.padded_size), range.end - range.start, self.size, self.padded_size)
+        }
+    }
+
+    pub fn window_mut(&mut self, range: Range<usize>) -> BufferViewMut<'_, T> {
+        self.check_window(&range);
+        unsafe {
+            view::view_mut(self.ptr.wrapping_add(range.start * self.padded_size), range.end - range.start, self.size, self.padded_size)
+        }
+    }
+
+    fn check_window(&self, range: &Range<usize>) {
+        assert!(range.start <= range.end, "window starts at {} but ends at {}", range.start, range.end);
+        assert!(range.end <= self.cap, "window end {} is out of range for cap {}", range.end, self.cap);
+    }
+
+    /// Borrows the `size` bytes of each entry, excluding any padding
+    pub fn views(&self) -> Vec<&[u8]> {
+        self.byte_entries().collect()
+    }
+
+    pub fn views_mut(&mut self) -> Vec<&mut [u8]> {
+        self.byte_entries_mut().collect()
+    }
+
+    /// Yields the `size` bytes of each entry in order, skipping padding, without collecting them
+    pub fn byte_entries(&self) -> impl Iterator<Item = &[u8]> {
+        let size = self.size;
+        self.data()
+            .chunks(self.padded_size)
+            .map(move |slot| &slot[..size])
+    }
+
+    pub fn byte_entries_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let (size, padded_size) = (self.size, self.padded_size);
+        self.data_mut()
+            .chunks_mut(padded_size)
+            .map(move |slot| &mut slot[..size])
+    }
+
+    /// Copies the `size` bytes of slot `index` into the front of `out`
+    pub fn read_entry_bytes(&self, index: usize, out: &mut [u8]) -> Result<(), Error> {
+        if index >= self.cap {
+            return Err(Error::IndexOutOfBounds);
+        }
+        if out.len() < self.size {
+            return Err(Error::SliceTooShort);
+        }
+        let offset = slot_offset(index, self.padded_size);
+        out[..self.size].copy_from_slice(&self.data()[offset..offset + self.size]);
+        Ok(())
+    }
+
+    /// Copies `src` over the leading bytes of slot `index`, it may be shorter than `size`
+    /// but never spills into the padding
+    pub fn write_entry_bytes(&mut self, index: usize, src: &[u8]) -> Result<(), Error> {
+        if index >= self.cap {
+            return Err(Error::IndexOutOfBounds);
+        }
+        if src.len() > self.size {
+            return Err(Error::CapacityExceeded);
+        }
+        let offset = slot_offset(index, self.padded_size);
+        self.data_mut()[offset..offset + src.len()].copy_from_slice(src);
+        Ok(())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(self.ptr, self.data_size())
+        }
+    }
+
+    /// The whole region, padding included, along with the alignment its base is guaranteed to have,
+    /// for consumers like DMA or SIMD code that need to check their preconditions
+    pub fn as_aligned_bytes(&self) -> (&[u8], usize) {
+        (self.data(), self.layout.align())
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr, self.data_size())
+        }
+    }
+
+    /// 64 bit FNV-1a hash of each entry's `size` bytes, padding is skipped so the value only
+    /// depends on the entries, not on the padding the buffer was built with
+    pub fn checksum(&self) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+        for entry in self.byte_entries() {
+            for &byte in entry {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+            }
+        }
+        hash
+    }
+
+    /// Whether the entries still hash to a `checksum` taken earlier
+    pub fn verify(&self, expected: u64) -> bool {
+        self.checksum() == expected
+    }
+
+    /// Renders `data()`, padding included, as an `xxd` style table, see `hexdump_to`
+    pub fn hexdump(&self) -> String {
+        let mut out = String::new();
+        self.hexdump_to(&mut out).expect("writing to a String never fails");
+        out
+    }
+
+    /// Writes `data()` as `xxd` style lines of 16 bytes: the offset, the bytes in hex pairs
+    /// and an ASCII gutter where unprintable bytes show as `.`
+    pub fn hexdump_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        for (line, chunk) in self.data().chunks(16).enumerate() {
+            write!(w, "{:08x}:", line * 16)?;
+            for column in 0..16 {
+                if column % 2 == 0 {
+                    w.write_char(' ')?;
+                }
+                match chunk.get(column) {
+                    Some(byte) => write!(w, "{:02x}", byte)?,
+                    None => w.write_str("  ")?,
+                }
+            }
+            w.write_str("  ")?;
+            for &byte in chunk {
+                w.write_char(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })?;
+            }
+            w.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    /// Sequential writer filling the slots in order from the first
+    pub fn cursor_mut(&mut self) -> Cursor<'_, T> {
+        unsafe {
+            cursor::cursor(self.ptr, self.cap, self.padded_size, self.initialized.as_deref_mut())
+        }
+    }
+
+    /// Byte source over the whole allocation, starting at offset 0
+    #[cfg(feature = "std")]
+    pub fn reader(&self) -> BufferReader<'_> {
+        BufferReader::new(self)
+    }
+
+    /// Byte sink over the whole allocation, starting at offset 0
+    #[cfg(feature = "std")]
+    pub fn writer(&mut self) -> BufferWriter<'_> {
+        BufferWriter::new(self)
+    }
+
+    /// `bytes::Buf` over the whole allocation, starting at offset 0
+    #[cfg(feature = "bytes")]
+    pub fn buf(&self) -> BufferBuf<'_> {
+        BufferBuf::new(self)
+    }
+
+    /// `bytes::BufMut` filling the whole allocation, starting at offset 0
+    #[cfg(feature = "bytes")]
+    pub fn buf_mut(&mut self) -> BufferBufMut<'_> {
+        BufferBufMut::new(self)
+    }
+
+    /// Layout for C callers walking the entries by `stride`, ownership stays with the buffer
+    pub fn as_ffi(&self) -> BufferFfi {
+        BufferFfi {
+            ptr: self.ptr,
+            len: self.size,
+            stride: self.padded_size,
+            count: self.cap,
+        }
+    }
+
+    /// Whether entries are packed back to back, so the allocation already is a native `[T]`
+    pub fn is_contiguous(&self) -> bool {
+        self.padded_size == self.size
+    }
+
+    /// Copies the entries, stripping any padding, into a packed `Vec`
+    pub fn to_contiguous(&self) -> Vec<T> where T: Copy {
+        self.iter().copied().collect()
+    }
+
+    /// Copies the entries, without padding, into an owned `Vec` and releases the buffer
+    pub fn into_vec(self) -> Vec<T> where T: Copy {
+        self.to_contiguous()
+    }
+
+    /// Copies the backing bytes, including padding, into an owned `Vec`
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data().to_vec()
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> Buffer<'a, MaybeUninit<T>, A> {
+    /// Reuses the allocation as initialized entries.
+    ///
+    /// # Safety
+    ///
+    /// Every slot must have been written with a valid `T`.
+    pub unsafe fn assume_init(self) -> Buffer<'a, T, A> {
+        let (cap, size, padded_size) = (self.cap, self.size, self.padded_size);
+        self.retype(cap, size, padded_size)
+    }
+}
+
+// `ptr` uniquely owns its allocation, like `Vec`'s, so moving a buffer to another thread moves the
+// entries with it and requires `T: Send`. Through `&self` only `&T` is handed out, mutation needs
+// `&mut self`, so sharing a buffer is sharing `&T` and requires `T: Sync`. The raw pointer opts out of
+// both auto traits, hence the manual impls, while `PhantomData<&'a mut T>` ties entries to `'a`.
+unsafe impl<'a, T: Send + 'a, A: Allocator + Send> Send for Buffer<'a, T, A> {}
+unsafe impl<'a, T: Sync + 'a, A: Allocator + Sync> Sync for Buffer<'a, T, A> {}
+
+// Per-slot atomic views for the integer types that have a matching atomic
+macro_rules! atomic_slice {
+    ($int:ty, $atomic:ident, $width:literal) => {
+        #[cfg(target_has_atomic = $width)]
+        impl <'a, A: Allocator> Buffer<'a, $int, A> {
+            /// Views the entries as atomics so slots can be updated from several threads.
+            /// Needs contiguous entries and a base pointer aligned for the atomic type, which may be
+            /// stricter than the integer's own alignment, otherwise `None` is returned.
+            /// Takes `&mut self` like `from_mut_slice`, so no plain reference can read a slot while
+            /// another thread stores to it through the view.
+            pub fn as_atomic_slice(&mut self) -> Option<&[::core::sync::atomic::$atomic]> {
+                let align = mem::align_of::<::core::sync::atomic::$atomic>();
+                if self.padded_size != self.size || !(self.ptr as usize).is_multiple_of(align) {
+                    return None;
+                }
+                Some(unsafe {
+                    slice::from_raw_parts(self.ptr as *const ::core::sync::atomic::$atomic, self.cap)
+                })
+            }
+        }
+    };
+}
+
+atomic_slice!(u32, AtomicU32, "32");
+atomic_slice!(u64, AtomicU64, "64");
+atomic_slice!(usize, AtomicUsize, "ptr");
+
+// Fixed endianness loads and stores at a slot's offset, for buffers used as wire protocol scratch space
+macro_rules! endian_access {
+    ($int:ident, $width:literal, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+        impl <'a, T: 'a, A: Allocator> Buffer<'a, T, A> {
+            #[doc = concat!("Reads a little endian `", stringify!($int), "` from the start of slot `index`")]
+            pub fn $read_le(&self, index: usize) -> $int {
+                $int::from_le_bytes(self.read_bytes_at::<$width>(index))
+            }
+
+            #[doc = concat!("Reads a big endian `", stringify!($int), "` from the start of slot `index`")]
+            pub fn $read_be(&self, index: usize) -> $int {
+                $int::from_be_bytes(self.read_bytes_at::<$width>(index))
+            }
+
+            #[doc = concat!("Writes `value` little endian at the start of slot `index`")]
+            pub fn $write_le(&mut self, index: usize, value: $int) {
+                self.write_bytes_at(index, &value.to_le_bytes())
+            }
+
+            #[doc = concat!("Writes `value` big endian at the start of slot `index`")]
+            pub fn $write_be(&mut self, index: usize, value: $int) {
+                self.write_bytes_at(index, &value.to_be_bytes())
+            }
+        }
+    };
+}
+
+endian_access!(u16, 2, read_u16_le, read_u16_be, write_u16_le, write_u16_be);
+endian_access!(u32, 4, read_u32_le, read_u32_be, write_u32_le, write_u32_be);
+endian_access!(u64, 8, read_u64_le, read_u64_be, write_u64_le, write_u64_be);
+
+impl <'a, T: 'a, A: Allocator> Buffer<'a, T, A> {
+    // Offset of slot `index`, panicking unless `len` bytes from there stay inside the allocation
+    fn checked_byte_range(&self, index: usize, len: usize) -> usize {
+        let offset = slot_offset(index, self.padded_size);
+        assert!(index < self.cap && offset + len <= self.data_size,
+            "{} bytes at slot {} overrun the {} byte buffer", len, index, self.data_size);
+        offset
+    }
+
+    fn read_bytes_at<const N: usize>(&self, index: usize) -> [u8; N] {
+        let offset = self.checked_byte_range(index, N);
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data()[offset..offset + N]);
+        bytes
+    }
+
+    fn write_bytes_at(&mut self, index: usize, bytes: &[u8]) {
+        let offset = self.checked_byte_range(index, bytes.len());
+        self.data_mut()[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl <'a, T: Copy + 'a, A: Allocator + Clone> Buffer<'a, T, A> {
+    /// Byte-wise copy into a fresh allocation with the same layout, failing with
+    /// `InsufficientMemory` instead of aborting when the allocator runs out.
+    /// Clones of secure buffers are wiped on drop too, and those of locked buffers are locked
+    /// before anything is copied in. Bytes go straight from one allocation to the other, no
+    /// temporary copy is ever made.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let alloc = self.alloc.clone();
+        let raw_ptr = alloc.allocate_zeroed(self.layout)
+            .map(|ptr| ptr.cast::<u8>().as_ptr())
+            .unwrap_or(ptr::null_mut());
+        let mut buffer = buffer_at(raw_ptr, self.layout, self.cap, self.size, self.padded_size, self.data_size, alloc)?;
+        #[cfg(feature = "std")]
+        {
+            buffer.secure = self.secure;
+            if self.locked {
+                buffer.lock()?;
+                buffer.locked = true;
+            }
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr, buffer.ptr, self.data_size)
+        }
+        buffer.padding = self.padding;
+        buffer.canary = self.canary;
+        buffer.len = self.len;
+        buffer.initialized = self.initialized.clone();
+        buffer.occupancy = self.occupancy.clone();
+        buffer.write_canary();
+        Ok(buffer)
+    }
+}
+
+/// Byte-wise copy into a fresh allocation with the same layout, `T::clone` is never called.
+/// Like other collections, cloning aborts when the allocator fails, see `try_clone`.
+/// Cloning a locked buffer panics when the clone can't be locked.
+impl <'a, T: Copy + 'a, A: Allocator + Clone> Clone for Buffer<'a, T, A> {
+    fn clone(&self) -> Self {
+        match self.try_clone() {
+            Ok(buffer) => buffer,
+            Err(Error::LockFailed) => panic!("failed to lock the clone of a locked buffer"),
+            Err(_) => handle_alloc_error(self.layout),
+        }
+    }
+}
+
+/// Zero capacity buffer, no allocation is made until a buffer is constructed with a capacity
+impl <'a, T: 'a, A: Allocator + Default> Default for Buffer<'a, T, A> {
+    fn default() -> Self {
+        let size = mem::size_of::<T>();
+        empty(size, size, A::default())
+    }
+}
+
+// Entries shown by `Debug` before the rest are elided
+const DEBUG_ENTRIES: usize = 8;
+
+struct DebugEntries<'b, 'a: 'b, T: 'a, A: Allocator + 'b>(&'b Buffer<'a, T, A>);
+
+impl <'b, 'a: 'b, T: fmt::Debug + 'a, A: Allocator + 'b> fmt::Debug for DebugEntries<'b, 'a, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut list = f.debug_list();
+        list.entries(self.0.iter().take(DEBUG_ENTRIES));
+        if self.0.cap > DEBUG_ENTRIES {
+            list.entry(&format_args!("..."));
+        }
+        list.finish()
+    }
+}
+
+/// Shows the layout and the first few entries rather than the raw pointer
+impl <'a, T: fmt::Debug + 'a, A: Allocator> fmt::Debug for Buffer<'a, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("cap", &self.cap)
+            .field("size", &self.size)
+            .field("padded_size", &self.padded_size)
+            .field("entries", &DebugEntries(self))
+            .finish()
+    }
+}
+
+/// Buffers are equal when they hold equal entries in the same order, padding is never compared
+impl <'a, 'b, T: PartialEq + 'a, A: Allocator, B: Allocator> PartialEq<Buffer<'b, T, B>> for Buffer<'a, T, A> {
+    fn eq(&self, other: &Buffer<'b, T, B>) -> bool {
+        self.cap == other.cap && self.iter().eq(other.iter())
+    }
+}
+
+impl <'a, T: Eq + 'a, A: Allocator> Eq for Buffer<'a, T, A> {}
+
+/// Hashes `cap` then each entry in order, consistent with `PartialEq` so padding never contributes
+impl <'a, T: Hash + 'a, A: Allocator> Hash for Buffer<'a, T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.cap.hash(state);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+/// Copies the elements into an unpadded buffer of `vec.len()` slots, an empty `Vec` gives an empty buffer
+impl <'a, T: Copy + 'a> TryFrom<Vec<T>> for Buffer<'a, T> {
+    type Error = Error;
+
+    fn try_from(vec: Vec<T>) -> Result<Self, Error> {
+        let mut buffer = Buffer::new(vec.len())?;
+        buffer.copy_from_slice(&vec)?;
+        Ok(buffer)
+    }
+}
+
+/// The whole allocation, padding included, as `data()` returns it
+impl <'a, T: 'a, A: Allocator> AsRef<[u8]> for Buffer<'a, T, A> {
+    fn as_ref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> AsMut<[u8]> for Buffer<'a, T, A> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.data_mut()
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> Index<usize> for Buffer<'a, T, A> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let cap = self.cap;
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", cap, index))
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> IndexMut<usize> for Buffer<'a, T, A> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let cap = self.cap;
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", cap, index))
+    }
+}
+
+/// Walks the entries at the padded stride without going through `entries`
+pub struct Iter<'b, T: 'b> {
+    ptr: *mut u8,
+    index: usize,
+    cap: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b T>,
+}
+
+impl <'b, T: 'b> Iterator for Iter<'b, T> {
+    type Item = &'b T;
+
+    fn next(&mut self) -> Option<&'b T> {
+        if self.index >= self.cap {
+            return None;
+        }
+        let entry = unsafe {
+            &*(self.ptr.add(slot_offset(self.index, self.padded_size)) as *const T)
+        };
+        self.index += 1;
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.cap - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+// Walks back from the last slot, `cap` doubles as the end of the range still to be visited
+impl <'b, T: 'b> DoubleEndedIterator for Iter<'b, T> {
+    fn next_back(&mut self) -> Option<&'b T> {
+        if self.index >= self.cap {
+            return None;
+        }
+        self.cap -= 1;
+        Some(unsafe {
+            &*(self.ptr.add(slot_offset(self.cap, self.padded_size)) as *const T)
+        })
+    }
+}
+
+impl <'b, T: 'b> ExactSizeIterator for Iter<'b, T> {}
+
+pub struct IterMut<'b, T: 'b> {
+    ptr: *mut u8,
+    index: usize,
+    cap: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b mut T>,
+}
+
+impl <'b, T: 'b> Iterator for IterMut<'b, T> {
+    type Item = &'b mut T;
+
+    fn next(&mut self) -> Option<&'b mut T> {
+        if self.index >= self.cap {
+            return None;
+        }
+        // Each index is visited once so the handed out references never alias
+        let entry = unsafe {
+            &mut *(self.ptr.add(slot_offset(self.index, self.padded_size)) as *mut T)
+        };
+        self.index += 1;
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.cap - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl <'b, T: 'b> DoubleEndedIterator for IterMut<'b, T> {
+    fn next_back(&mut self) -> Option<&'b mut T> {
+        if self.index >= self.cap {
+            return None;
+        }
+        self.cap -= 1;
+        Some(unsafe {
+            &mut *(self.ptr.add(slot_offset(self.cap, self.padded_size)) as *mut T)
+        })
+    }
+}
+
+impl <'b, T: 'b> ExactSizeIterator for IterMut<'b, T> {}
+
+impl <'a, 'b, T: 'a, A: Allocator> IntoIterator for &'b Buffer<'a, T, A> {
+    type Item = &'b T;
+    type IntoIter = Iter<'b, T>;
+
+    fn into_iter(self) -> Iter<'b, T> {
+        self.iter()
+    }
+}
+
+impl <'a, 'b, T: 'a, A: Allocator> IntoIterator for &'b mut Buffer<'a, T, A> {
+    type Item = &'b mut T;
+    type IntoIter = IterMut<'b, T>;
+
+    fn into_iter(self) -> IterMut<'b, T> {
+        self.iter_mut()
+    }
+}
+
+impl <'a, T: 'a, A: Allocator> Drop for Buffer<'a, T, A> {
+    fn drop(&mut self) {
+        // `entries` only holds references into the allocation, so only tracked values are dropped
+        self.drop_initialized();
+        self.release();
+    }
+}
+
+// We need to guarantee the following:
+// * We don't ever allocate `> isize::MAX` byte-size objects
+// * We don't overflow `usize::MAX` and actually allocate too little
+//
+// On 64-bit we just need to check for overflow since trying to allocate
+// `> isize::MAX` bytes will surely fail. On 32-bit and 16-bit we need to add
+// an extra guard for this in case we're running on a platform which can use
+// all 4GB in user-space. e.g. PAE or x32
+// Remaining successful locks before `lock_range` starts failing, so tests can reach the failure paths
+// even when running with the privilege to lock without limit
+#[cfg(all(test, feature = "std"))]
+std::thread_local! {
+    static LOCKS_LEFT: core::cell::Cell<Option<usize>> = const { core::cell::Cell::new(None) };
+}
+
+#[cfg(feature = "std")]
+fn lock_range(ptr: *mut u8, len: usize) -> bool {
+    #[cfg(test)]
+    {
+        let left = LOCKS_LEFT.with(|left| left.get());
+        match left {
+            Some(0) => return false,
+            Some(n) => LOCKS_LEFT.with(|left| left.set(Some(n - 1))),
+            None => {},
+        }
+    }
+    unsafe {
+        memsec::mlock(ptr, len)
+    }
+}
+
+#[inline]
+fn alloc_guard(alloc_size: usize) -> Result<usize, Error> {
+    if mem::size_of::<usize>() < 8 && alloc_size > isize::MAX as usize {
+        Err(Error::AllocCapacityOverflow)
+    } else {
+        Ok(alloc_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate env_logger;
+    use super::*;
+    use std::sync::Mutex;
+    use allocator_api2::alloc::AllocError;
+    use std::cell::Cell;
+
+    #[derive(Clone, Copy, Debug)]
+    struct Thing {
+        value1: u64,
+        value2: u64,
+    }
+
+    #[test]
+    fn should_display_human_readable_errors() {
+        assert_eq!(Error::AllocCapacityOverflow.to_string(), "allocation exceeds the maximum size supported by the platform");
+        assert_eq!(Error::BufferSizeOverflow.to_string(), "buffer size overflows usize");
+        assert_eq!(Error::CapacityExceeded.to_string(), "buffer capacity exceeded");
+        assert_eq!(Error::CapacityTooSmall.to_string(), "requested capacity is smaller than the current capacity");
+        assert_eq!(Error::AdviseFailed.to_string(), "failed to apply paging advice to the allocation");
+        assert_eq!(Error::IncompatibleCast.to_string(), "entry type is incompatible with the buffer's alignment or stride");
+        assert_eq!(Error::IndexOutOfBounds.to_string(), "index is beyond the buffer's capacity");
+        assert_eq!(Error::InsufficientMemory.to_string(), "insufficient memory to allocate buffer");
+        assert_eq!(Error::InvalidAlignment.to_string(), "alignment must be a power of two no smaller than the entry's alignment");
+        assert_eq!(Error::LayoutMismatch.to_string(), "buffers differ in capacity or stride");
+        assert_eq!(Error::LockFailed.to_string(), "failed to lock buffer memory");
+        assert_eq!(Error::MisalignedPadding { padded_size: 18, align: 8 }.to_string(), "padded size 18 is not a multiple of alignment 8");
+        assert_eq!(Error::MapFailed.to_string(), "failed to map the backing file");
+        assert_eq!(Error::NumaUnavailable.to_string(), "numa node binding is not available");
+        assert_eq!(Error::SharedMemoryFailed.to_string(), "failed to open or map the shared memory object");
+        assert_eq!(Error::SliceTooShort.to_string(), "slice is shorter than an entry");
+        assert_eq!(Error::ZeroBufferNotSupported.to_string(), "zero sized buffers are not supported");
+    }
+
+    #[test]
+    fn should_expose_layout_error_as_source() {
+        use std::error::Error as StdError;
+        let error = Error::from(Layout::from_size_align(8, 3).unwrap_err());
+        assert!(error.to_string().starts_with("invalid buffer layout: "));
+        assert!(error.source().is_some());
+        assert!(Error::InsufficientMemory.source().is_none());
+    }
+
+    #[test]
+    fn should_capture_correct_properties_for_u8() {
+        let buf = Buffer::<u8>::new(1).unwrap();
+        assert_eq!(buf.cap(), 1);
+        assert_eq!(buf.size(), 1);
+        assert_eq!(buf.padded_size(), 1);
+    }
+
+    #[test]
+    fn should_capture_correct_properties_for_u32() {
+        let buf = Buffer::<u32>::new(1).unwrap();
+        assert_eq!(buf.cap(), 1);
+        assert_eq!(buf.size(), 4);
+        assert_eq!(buf.padded_size(), 4);
+    }
+
+    #[test]
+    fn should_capture_correct_properties_for_struct_thing() {
+        let buf = Buffer::<Thing>::new(1).unwrap();
+        assert_eq!(buf.cap(), 1);
+        assert_eq!(buf.size(), 16);
+        assert_eq!(buf.padded_size(), 16);
+    }
+
+    #[test]
+    fn should_capture_correct_properties_for_struct_thing_with_padding() {
+        let buf = Buffer::<Thing>::padded(1, 64).unwrap();
+        assert_eq!(buf.cap(), 1);
+        assert_eq!(buf.size(), 16);
+        assert_eq!(buf.padded_size(), 64);
+    }
+
+    #[test]
+    fn should_expand_buffer_entries_in_memory_but_not_views() {
+        let buf = Buffer::<Thing>::padded(1, 64).unwrap();
+        let views = buf.views();
+        assert_eq!(views[0].len(), 16);
+    }
+
+    #[test]
+    fn should_calculate_data_size_to_match_underying_buffer_size_for_unpadded() {
+        let buf = Buffer::<u8>::new(1).unwrap();
+        let data = buf.data();
+        assert_eq!(data.len(), buf.data_size());
+    }
+
+    #[test]
+    fn should_place_updated_data_propertly_in_second_slot() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf[1] = 12;
+        assert_eq!(&[0, 12], buf.data());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn should_advise_page_aligned_buffers_only() {
+        let mut buf = Buffer::<[u8; 4096]>::aligned(4, 4096).unwrap();
+        buf.advise(Advice::WillNeed).unwrap();
+        buf.advise(Advice::Sequential).unwrap();
+        buf.advise(Advice::DontNeed).unwrap();
+        let mut offset = Buffer::<u8>::new(8192).unwrap();
+        if (offset.as_byte_ptr() as usize).is_multiple_of(4096) {
+            return; // The heap happened to hand out a page aligned block
+        }
+        match offset.advise(Advice::Random) {
+            Err(Error::AdviseFailed) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_lend_data_as_byte_slices() {
+        fn sum(bytes: impl AsRef<[u8]>) -> u32 {
+            bytes.as_ref().iter().map(|b| u32::from(*b)).sum()
+        }
+        let mut buf = Buffer::<u8>::new(4).unwrap();
+        buf.as_mut().copy_from_slice(&[1, 2, 3, 250]);
+        assert_eq!(sum(&buf), 256);
+        let padded = Buffer::<u16>::padded(3, 4).unwrap();
+        assert_eq!(padded.as_ref().len(), padded.data_size());
+        #[cfg(feature = "std")]
+        {
+            let mut out = Vec::new();
+            std::io::copy(&mut AsRef::<[u8]>::as_ref(&buf), &mut out).unwrap();
+            assert_eq!(out, vec![1, 2, 3, 250]);
+        }
+    }
+
+    #[test]
+    fn should_write_through_entries_mut() {
+        let mut buf = Buffer::<Thing>::padded(3, 24).unwrap();
+        let mut entries = buf.entries_mut();
+        let last = entries.pop().unwrap();
+        last.value1 = 5;
+        entries[0].value2 = 7;
+        assert_eq!(entries.len(), 2);
+        assert_eq!((buf[0].value2, buf[2].value1), (7, 5));
+        assert_eq!(&buf.data()[48..56], &5u64.to_ne_bytes());
+    }
+
+    #[test]
+    fn should_update_struct_in_data_properly() {
+        let mut buf = Buffer::<Thing>::new(2).unwrap();
+        buf[0].value2 = 36;
+        buf[1].value1 = 12;
+        assert_eq!(&[0,0,0,0,0,0,0,0, 36,0,0,0,0,0,0,0, 12,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_place_updated_data_propertly_in_second_slot_with_padding() {
+        let mut buf = Buffer::<u8>::padded(2, 4).unwrap();
+        buf[1] = 12;
+        assert_eq!(&[0,0,0,0, 12,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_update_struct_in_data_properly_with_padding() {
+        let mut buf = Buffer::<Thing>::padded(2, 24).unwrap();
+        buf[0].value2 = 36;
+        buf[1].value1 = 12;
+        assert_eq!(&[0,0,0,0,0,0,0,0, 36,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0, 12,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_reject_padding_that_misaligns_entries() {
+        match Buffer::<Thing>::padded(2, 18) {
+            Err(Error::MisalignedPadding { padded_size: 18, align: 8 }) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_store_data_size_matching_cap_times_padded_size() {
+        let bufs = vec![
+            Buffer::<Thing>::new(3).unwrap(),
+            Buffer::<Thing>::padded(3, 24).unwrap(),
+            Buffer::<Thing>::cache_aligned(3).unwrap(),
+            Buffer::<Thing>::pow2(3).unwrap(),
+        ];
+        for buf in bufs {
+            assert_eq!(buf.data_size(), buf.cap() * buf.padded_size());
+            assert_eq!(buf.data().len(), buf.data_size());
+        }
+    }
+
+    #[test]
+    fn should_map_null_allocation_to_insufficient_memory() {
+        let layout = Layout::from_size_align(8, 8).unwrap();
+        match buffer_at::<u64, Global>(ptr::null_mut(), layout, 1, 8, 8, 8, Global) {
+            Err(Error::InsufficientMemory) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_wipe_secure_buffer_contents() {
+        let mut buf = Buffer::<u64>::secure(4).unwrap();
+        assert!(buf.is_secure());
+        buf.fill(0xDEAD_BEEF);
+        buf.wipe();
+        let bytes = unsafe { slice::from_raw_parts(buf.as_byte_ptr(), buf.data_size()) };
+        assert!(bytes.iter().all(|b| *b == 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_lock_small_buffer_or_report_lock_failure() {
+        let buf = Buffer::<u64>::new(4).unwrap();
+        match buf.lock() {
+            Ok(()) => buf.unlock().unwrap(),
+            Err(Error::LockFailed) => {}, // e.g. RLIMIT_MEMLOCK exceeded
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_construct_secure_locked_buffer() {
+        match Buffer::<u64>::secure_locked(4) {
+            Ok(buf) => assert!(buf.is_secure()),
+            Err(Error::LockFailed) => {},
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    struct CountingAllocator {
+        allocations: Cell<usize>,
+        deallocations: Cell<usize>,
+    }
+
+    unsafe impl Allocator for &CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.allocations.set(self.allocations.get() + 1);
+            Global.allocate_zeroed(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.deallocations.set(self.deallocations.get() + 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    // Hands out `remaining` allocations, then fails every later one like an exhausted heap
+    #[derive(Clone)]
+    struct LimitedAllocator<'l> {
+        remaining: &'l Cell<usize>,
+    }
+
+    unsafe impl<'l> Allocator for LimitedAllocator<'l> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            match self.remaining.get() {
+                0 => Err(AllocError),
+                remaining => {
+                    self.remaining.set(remaining - 1);
+                    Global.allocate(layout)
+                },
+            }
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn should_report_insufficient_memory_instead_of_aborting() {
+        let remaining = Cell::new(0);
+        match Buffer::<u64, _>::new_in(4, LimitedAllocator { remaining: &remaining }) {
+            Err(Error::InsufficientMemory) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        remaining.set(1);
+        let mut buf = Buffer::<u64, _>::new_in(4, LimitedAllocator { remaining: &remaining }).unwrap();
+        buf[0] = 7;
+        match buf.try_clone() {
+            Err(Error::InsufficientMemory) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match buf.grow(8) {
+            Err(Error::InsufficientMemory) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!((buf.cap(), buf[0]), (4, 7));
+        remaining.set(1);
+        assert_eq!(buf.try_clone().unwrap(), buf);
+    }
+
+    // Counts allocations released while still holding non zero bytes
+    struct WipeChecking {
+        dirty: Cell<usize>,
+    }
+
+    unsafe impl Allocator for &WipeChecking {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            if slice::from_raw_parts(ptr.as_ptr(), layout.size()).iter().any(|b| *b != 0) {
+                self.dirty.set(self.dirty.get() + 1);
+            }
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_clone_secure_buffers_locked_and_wiped_on_drop() {
+        let checking = WipeChecking { dirty: Cell::new(0) };
+        let mut buf = Buffer::<u64, _>::new_in(4, &checking).unwrap();
+        buf.secure = true;
+        match buf.lock() {
+            Ok(()) => buf.locked = true,
+            Err(Error::LockFailed) => return, // e.g. RLIMIT_MEMLOCK exceeded
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+        buf.fill(0x5EC2E7);
+        let clone = buf.try_clone().unwrap();
+        assert!(clone.is_secure() && clone.locked);
+        assert_eq!(clone.to_contiguous(), vec![0x5EC2E7; 4]);
+        drop(clone);
+        drop(buf);
+        assert_eq!(checking.dirty.get(), 0);
+        let plain = Buffer::<u64, _>::new_in(4, &checking).unwrap();
+        let mut copy = plain.clone();
+        copy.fill(1);
+        assert!(!copy.is_secure() && !copy.locked);
+        drop(copy);
+        assert_eq!(checking.dirty.get(), 1);
+    }
+
+    // Keeps every formatted record, tests pick out their own by an unusual capacity
+    struct CapturingLogger(Mutex<Vec<String>>);
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+
+    #[test]
+    fn should_log_allocation_and_release() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+        drop(Buffer::<u64>::new(4093).unwrap());
+        let _ = Buffer::<u64>::padded(usize::MAX / 4, 8);
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.iter().any(|record| record == "buffer allocated: cap=4093 size=8 padded_size=8 alloc_size=32744 align=8"));
+        assert!(records.iter().any(|record| record == "buffer released: cap=4093 alloc_size=32744"));
+        assert!(records.iter().any(|record| record.starts_with(&format!("buffer rejected: cap={}", usize::MAX / 4))));
+    }
+
+    #[test]
+    fn should_log_allocations_made_while_growing() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+        let mut buf = Buffer::<u64>::new(4091).unwrap();
+        buf.grow(4092).unwrap();
+        let remaining = Cell::new(1);
+        let mut limited = Buffer::<u32, _>::new_in(4089, LimitedAllocator { remaining: &remaining }).unwrap();
+        assert!(limited.grow(4090).is_err());
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.iter().any(|record| record == "buffer allocated: cap=4092 size=8 padded_size=8 alloc_size=32736 align=8"));
+        assert!(records.iter().any(|record| record == "buffer released: cap=4091 alloc_size=32728"));
+        assert!(records.iter().any(|record| record == "buffer allocation failed: alloc_size=16360 align=4"));
+    }
+
+    #[test]
+    fn should_allocate_and_deallocate_once_through_custom_allocator() {
+        let counting = CountingAllocator { allocations: Cell::new(0), deallocations: Cell::new(0) };
+        {
+            let mut buf = Buffer::<u32, _>::new_in(4, &counting).unwrap();
+            buf[3] = 8;
+            assert_eq!(counting.allocations.get(), 1);
+            assert_eq!(counting.deallocations.get(), 0);
+        }
+        assert_eq!(counting.allocations.get(), 1);
+        assert_eq!(counting.deallocations.get(), 1);
+    }
+
+    #[test]
+    fn should_rebuild_buffer_from_raw_parts_and_free_once() {
+        let counting = CountingAllocator { allocations: Cell::new(0), deallocations: Cell::new(0) };
+        {
+            let mut buf = Buffer::<u32, _>::new_in(3, &counting).unwrap();
+            buf.copy_from_slice(&[4, 5, 6]).unwrap();
+            let (ptr, cap, size, padded_size, layout) = buf.into_raw();
+            assert_eq!((cap, size, padded_size, layout.size()), (3, 4, 4, 12));
+            assert_eq!(counting.deallocations.get(), 0);
+            let buf = unsafe { Buffer::<u32, _>::from_raw_parts_in(ptr, cap, size, padded_size, layout, &counting) };
+            assert_eq!(Some(&[4, 5, 6][..]), buf.as_slice());
+        }
+        assert_eq!(counting.allocations.get(), 1);
+        assert_eq!(counting.deallocations.get(), 1);
+    }
+
+    #[test]
+    fn should_round_trip_global_buffer_through_raw_parts() {
+        let buf = Buffer::from_iter_with_cap(1u64..4, 3).unwrap();
+        let (ptr, cap, size, padded_size, layout) = buf.into_raw();
+        let buf = unsafe { Buffer::<u64>::from_raw_parts(ptr, cap, size, padded_size, layout) };
+        assert_eq!(buf, Buffer::from_iter_with_cap(1u64..4, 3).unwrap());
+        let (ptr, cap, size, padded_size, layout) = Buffer::<u64>::new(0).unwrap().into_raw();
+        let empty = unsafe { Buffer::<u64>::from_raw_parts(ptr, cap, size, padded_size, layout) };
+        assert_eq!(empty.cap(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn should_read_and_write_huge_page_buffer() {
+        let mut buf = Buffer::<u64>::huge_page(1024).unwrap();
+        assert_eq!(buf.as_byte_ptr() as usize % HUGE_PAGE_SIZE, 0);
+        assert_eq!(buf.data_size(), 1024 * 8);
+        buf[0] = 1;
+        buf[512] = 2;
+        buf[1023] = 3;
+        assert_eq!((buf[0], buf[1], buf[512], buf[1023]), (1, 0, 2, 3));
+    }
+
+    #[cfg(feature = "numa")]
+    #[test]
+    fn should_use_buffer_on_numa_node_zero_or_report_unavailable() {
+        match Buffer::<u64>::on_numa_node(1024, 0) {
+            Ok(mut buf) => {
+                buf[1023] = 7;
+                assert_eq!((buf[0], buf[1023]), (0, 7));
+            },
+            Err(Error::NumaUnavailable) => {},
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn should_persist_mmap_file_writes_across_reopen() {
+        let path = std::env::temp_dir().join(format!("omni_buffer_mmap_{}", std::process::id()));
+        {
+            let mut buf = Buffer::<u64>::mmap_file(&path, 4).unwrap();
+            assert_eq!(buf.data_size(), 32);
+            buf[0] = 11;
+            buf[3] = 44;
+        }
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 32);
+        {
+            let buf = Buffer::<u64>::mmap_file(&path, 4).unwrap();
+            assert_eq!((buf[0], buf[1], buf[2], buf[3]), (11, 0, 0, 44));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Re-runs just the named test in a fresh process, with `var` set so the test knows it is the child
+    #[cfg(any(feature = "metrics", feature = "shm", feature = "guard-pages"))]
+    fn run_in_child(test: &str, var: &str, value: &str) -> std::process::ExitStatus {
+        std::process::Command::new(std::env::current_exe().unwrap())
+            .args([&format!("tests::{}", test), "--exact", "--quiet"])
+            .env(var, value)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap()
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn should_track_live_buffers_and_bytes() {
+        // Counters are global, so the exact counts are only checked in a process running nothing else
+        if std::env::var("OMNI_BUFFER_METRICS_CHILD").is_err() {
+            assert!(run_in_child("should_track_live_buffers_and_bytes", "OMNI_BUFFER_METRICS_CHILD", "1").success());
+            return;
+        }
+        let a = Buffer::<u64>::new(4).unwrap();
+        let b = Buffer::<u32>::new(2).unwrap();
+        assert_eq!((live_buffers(), allocated_bytes()), (2, 40));
+        drop(b);
+        assert_eq!((live_buffers(), allocated_bytes()), (1, 32));
+        let mut a = a;
+        a.grow(8).unwrap();
+        assert_eq!(AllocStats::current(), AllocStats { allocated_bytes: 64, live_buffers: 1 });
+        let (ptr, cap, size, padded_size, layout) = a.into_raw();
+        assert_eq!(live_buffers(), 0);
+        drop(unsafe { Buffer::<u64>::from_raw_parts(ptr, cap, size, padded_size, layout) });
+        assert_eq!((live_buffers(), allocated_bytes()), (0, 0));
+        let empty = Buffer::<u32>::new(0).unwrap();
+        drop(empty.clone());
+        drop(empty);
+        assert_eq!((live_buffers(), allocated_bytes()), (0, 0));
+    }
+
+    #[cfg(all(feature = "guard-pages", any(target_os = "linux", target_os = "macos")))]
+    #[test]
+    fn should_fault_when_writing_past_guarded_buffer() {
+        use std::os::unix::process::ExitStatusExt;
+        let mut buf = Buffer::<u32>::guarded(3).unwrap();
+        // The test binary re-runs just this test as the child, which writes one slot past the end
+        if std::env::var("OMNI_BUFFER_GUARD_CHILD").is_ok() {
+            unsafe {
+                ptr::write_volatile(buf.as_mut_ptr().add(buf.cap()), 1);
+            }
+            return;
+        }
+        buf[0] = 1;
+        buf[2] = 3;
+        assert_eq!((buf[0], buf[1], buf[2]), (1, 0, 3));
+        let status = run_in_child("should_fault_when_writing_past_guarded_buffer", "OMNI_BUFFER_GUARD_CHILD", "1");
+        let signal = status.signal();
+        assert!(signal == Some(libc::SIGSEGV) || signal == Some(libc::SIGBUS), "child exited with {:?}", status);
+    }
+
+    #[cfg(all(feature = "shm", unix))]
+    #[test]
+    fn should_share_slots_with_child_process_by_name() {
+        // The test binary re-runs just this test as the child, which writes a slot and exits
+        if let Ok(name) = std::env::var("OMNI_BUFFER_SHM_CHILD") {
+            let mut buf = Buffer::<u64>::shared_open(&name, 4).unwrap();
+            buf[2] = 42;
+            return;
+        }
+        let name = format!("/omni_buffer_shm_{}", std::process::id());
+        let buf = Buffer::<u64>::shared_create(&name, 4).unwrap();
+        assert!(Buffer::<u64>::shared_create(&name, 4).is_err());
+        let status = run_in_child("should_share_slots_with_child_process_by_name", "OMNI_BUFFER_SHM_CHILD", &name);
+        assert!(status.success());
+        assert_eq!(buf[2], 42);
+        buf.unlink().unwrap();
+        assert!(Buffer::<u64>::shared_open(&name, 4).is_err());
+    }
+
+    #[test]
+    fn should_write_uninit_buffer_and_assume_init() {
+        let mut buf = Buffer::<u32>::uninit(3).unwrap();
+        assert_eq!(buf.size(), 4);
+        for (i, entry) in buf.iter_mut().enumerate() {
+            entry.write(i as u32 * 10);
+        }
+        let buf = unsafe { buf.assume_init() };
+        assert_eq!(Some(&[0, 10, 20][..]), buf.as_slice());
+        assert_eq!(*buf.entries()[2], 20);
+    }
+
+    #[test]
+    fn should_clone_into_independent_allocation() {
+        let mut buf = Buffer::<Thing>::padded(2, 24).unwrap();
+        buf[0].value1 = 36;
+        buf[1].value2 = 12;
+        let mut clone = buf.clone();
+        assert_eq!(clone.data(), buf.data());
+        assert_eq!(clone.padded_size(), 24);
+        clone[1].value2 = 99;
+        clone[0] = Thing { value1: 1, value2: 2 };
+        assert_eq!((buf[0].value1, buf[0].value2, buf[1].value2), (36, 0, 12));
+        assert_eq!((clone[0].value1, clone[0].value2, clone[1].value2), (1, 2, 99));
+    }
+
+    #[test]
+    fn should_default_to_empty_buffer() {
+        let mut buf = Buffer::<u32>::default();
+        assert_eq!(buf.cap(), 0);
+        assert_eq!(buf.size(), 4);
+        assert_eq!(buf.data_size(), 0);
+        assert!(buf.data().is_empty());
+        assert_eq!(buf.iter().count(), 0);
+        assert_eq!(buf.iter_mut().count(), 0);
+        assert!(buf.get(0).is_none());
+        assert_eq!(Some(&[][..]), buf.as_slice());
+        buf.zero();
+        drop(buf);
+    }
+
+    #[test]
+    fn should_construct_empty_unpadded_buffer() {
+        let buf = Buffer::<u32>::new(0).unwrap();
+        assert_eq!(buf.cap(), 0);
+        assert!(buf.data().is_empty());
+        assert!(buf.entries().is_empty());
+        buf.dealloc();
+    }
+
+    #[test]
+    fn should_construct_empty_padded_buffer() {
+        let buf = Buffer::<u32>::padded(0, 64).unwrap();
+        assert_eq!(buf.cap(), 0);
+        assert_eq!(buf.padded_size(), 64);
+        assert_eq!(buf.data_size(), 0);
+        assert!(buf.views().is_empty());
+    }
+
+    #[test]
+    fn should_match_named_constructor_when_building_with_padding() {
+        let buf = Buffer::<u32>::with_padding(2, Padding::CacheAligned).unwrap();
+        let named = Buffer::<u32>::cache_aligned(2).unwrap();
+        assert_eq!((buf.cap(), buf.padded_size(), buf.data_size()), (named.cap(), named.padded_size(), named.data_size()));
+        assert_eq!(buf.padding_mode(), Padding::CacheAligned);
+        assert_eq!(named.padding_mode(), Padding::CacheAligned);
+    }
+
+    #[test]
+    fn should_round_trip_padding_mode() {
+        for &padding in &[Padding::None, Padding::Padded(16), Padding::CacheAligned, Padding::Pow2, Padding::Isolated] {
+            let buf = Buffer::<u32>::with_padding(3, padding).unwrap();
+            assert_eq!(buf.padding_mode(), padding);
+            assert_eq!(buf.clone().padding_mode(), padding);
+        }
+        assert_eq!(Buffer::<u32>::with_padding(0, Padding::Pow2).unwrap().padding_mode(), Padding::Pow2);
+    }
+
+    #[test]
+    fn should_align_base_pointer_and_every_slot() {
+        let mut buf = Buffer::<u64>::aligned(2, 32).unwrap();
+        assert_eq!(buf.alignment(), 32);
+        assert_eq!(buf.padded_size(), 32);
+        assert_eq!(buf.as_byte_ptr() as usize % 32, 0);
+        buf[1] = 5;
+        assert_eq!(buf.entry_ptr(1) as usize % 32, 0);
+        let buf = Buffer::<[u8; 40]>::aligned(3, 32).unwrap();
+        assert_eq!(buf.padded_size(), 64);
+        assert_eq!(buf.as_byte_ptr() as usize % 32, 0);
+    }
+
+    #[test]
+    fn should_reject_invalid_alignment() {
+        for &align in &[0, 3, 24, 4] {
+            match Buffer::<u64>::aligned(2, align) {
+                Err(Error::InvalidAlignment) => {},
+                result => panic!("unexpected result for {}: {:?}", align, result),
+            }
+        }
+    }
+
+    #[test]
+    fn should_detect_overrun_into_canary() {
+        let mut buf = Buffer::<u32>::with_canary(4).unwrap();
+        assert_eq!(buf.data_size(), 16);
+        buf.fill(u32::MAX);
+        assert!(buf.check_canary());
+        unsafe {
+            ptr::write(buf.as_mut_ptr().add(buf.cap()), 0);
+        }
+        assert!(!buf.check_canary());
+    }
+
+    #[test]
+    fn should_keep_canary_across_grow_and_clone() {
+        let mut buf = Buffer::<u32>::with_canary(2).unwrap();
+        buf.grow(5).unwrap();
+        assert!(buf.check_canary());
+        let copy = buf.clone();
+        assert!(copy.check_canary());
+        assert!(Buffer::<u32>::new(2).unwrap().check_canary());
+    }
+
+    #[test]
+    fn should_report_cache_alignment() {
+        // Aligned to the largest line size of any target, so the base pointer is a line multiple too
+        #[repr(align(256))]
+        struct Line { _bytes: [u8; 256] }
+        let buf = Buffer::<Line>::cache_aligned(2).unwrap();
+        assert_eq!(buf.alignment(), 256);
+        assert!(buf.is_cache_aligned());
+        let buf = Buffer::<u32>::new(16).unwrap();
+        assert_eq!(buf.alignment(), 4);
+        assert!(!buf.is_cache_aligned());
+    }
+
+    #[test]
+    fn should_construct_empty_cache_aligned_buffer() {
+        let buf = Buffer::<Thing>::cache_aligned(0).unwrap();
+        assert_eq!(buf.cap(), 0);
+        assert_eq!(buf.iter().count(), 0);
+    }
+
+    #[test]
+    fn should_reject_unpadded_zero_sized_types() {
+        match Buffer::<()>::new(2) {
+            Err(Error::ZeroBufferNotSupported) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_grow_and_keep_existing_values() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        buf.copy_from_slice(&[4, 5]).unwrap();
+        buf.grow(4).unwrap();
+        assert_eq!(buf.cap(), 4);
+        assert_eq!(buf.data_size(), 16);
+        assert_eq!(Some(&[4, 5, 0, 0][..]), buf.as_slice());
+        buf[3] = 7;
+        assert_eq!(buf[3], 7);
+    }
+
+    #[test]
+    fn should_grow_padded_and_empty_buffers() {
+        let mut buf = Buffer::<u32>::padded(0, 8).unwrap();
+        buf.grow(2).unwrap();
+        buf[1] = 3;
+        assert_eq!(&[0,0,0,0,0,0,0,0, 3,0,0,0,0,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_reject_growing_to_smaller_capacity() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        match buf.grow(1) {
+            Err(Error::CapacityTooSmall) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(buf.cap(), 2);
+    }
+
+    #[test]
+    fn should_shrink_and_keep_retained_slots() {
+        let mut buf = Buffer::<Thing>::padded(3, 24).unwrap();
+        buf[0].value1 = 36;
+        buf[1].value2 = 12;
+        buf[2].value1 = 99;
+        buf.shrink_to(2).unwrap();
+        assert_eq!(buf.cap(), 2);
+        assert_eq!(buf.entries().len(), 2);
+        assert_eq!(&[36,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0, 12,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_not_shrink_to_larger_capacity() {
+        let mut buf = Buffer::<Thing>::new(2).unwrap();
+        let ptr = buf.as_byte_ptr();
+        buf.shrink_to(5).unwrap();
+        assert_eq!(buf.cap(), 2);
+        assert_eq!(buf.as_byte_ptr(), ptr);
+    }
+
+    #[test]
+    fn should_shrink_to_empty() {
+        let mut buf = Buffer::<Thing>::new(2).unwrap();
+        buf.shrink_to(0).unwrap();
+        assert_eq!(buf.cap(), 0);
+        assert!(buf.data().is_empty());
+    }
+
+    #[test]
+    fn should_increment_shared_atomic_slot_from_several_threads() {
+        use std::sync::atomic::Ordering;
+        let mut buf = Buffer::<u64>::new(2).unwrap();
+        let slots = buf.as_atomic_slice().unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        slots[1].fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(buf[0], 0);
+        assert_eq!(buf[1], 4000);
+    }
+
+    #[test]
+    fn should_view_u32_and_usize_buffers_as_atomics() {
+        use std::sync::atomic::Ordering;
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.as_atomic_slice().unwrap()[2].store(5, Ordering::Relaxed);
+        assert_eq!(buf[2], 5);
+        let mut buf = Buffer::<usize>::new(1).unwrap();
+        assert_eq!(buf.as_atomic_slice().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn should_not_view_padded_entries_as_atomics() {
+        let mut buf = Buffer::<u64>::padded(2, 16).unwrap();
+        assert!(buf.as_atomic_slice().is_none());
+    }
+
+    #[test]
+    fn should_move_buffer_into_thread_and_back() {
+        use std::sync::mpsc;
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        buf[0] = 3;
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            buf[1] = buf[0] * 2;
+            sender.send(buf).unwrap();
+        });
+        let buf = receiver.recv().unwrap();
+        assert_eq!(Some(&[3, 6][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_read_shared_buffer_from_several_threads() {
+        let mut buf = Buffer::<u32>::new(4).unwrap();
+        buf.fill_with(|i| i as u32);
+        let buf = &buf;
+        let total: u32 = std::thread::scope(|scope| {
+            let left = scope.spawn(move || buf[0] + buf[1]);
+            let right = scope.spawn(move || buf[2] + buf[3]);
+            left.join().unwrap() + right.join().unwrap()
+        });
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn should_cast_u32_entries_into_u8_entries() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        buf[0] = 0x0403_0201;
+        buf[1] = 0x0807_0605;
+        let bytes = buf.data().to_vec();
+        let buf = unsafe { buf.cast::<u8>() }.unwrap();
+        assert_eq!(buf.cap(), 8);
+        assert_eq!(buf.size(), 1);
+        assert_eq!(buf.padded_size(), 1);
+        assert_eq!(buf.data(), &bytes[..]);
+        assert_eq!(buf.iter().cloned().collect::<Vec<u8>>(), bytes);
+        assert_eq!(*buf.entries()[7], bytes[7]);
+    }
+
+    #[test]
+    fn should_reject_cast_to_stricter_alignment() {
+        let buf = Buffer::<u8>::new(8).unwrap();
+        match unsafe { buf.cast::<u32>() } {
+            Err(Error::IncompatibleCast) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_reject_cast_that_does_not_divide_stride() {
+        let buf = Buffer::<[u8; 3]>::new(4).unwrap();
+        match unsafe { buf.cast::<[u8; 2]>() } {
+            Err(Error::IncompatibleCast) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_release_memory_when_buffer_leaves_scope() {
+        // Run under Miri to confirm the allocation is not reported as leaked
+        {
+            let mut buf = Buffer::<Thing>::new(2).unwrap();
+            buf[1].value1 = 12;
+        }
+        let buf = Buffer::<u8>::new(1).unwrap();
+        buf.dealloc();
+    }
+
+    #[test]
+    fn should_copy_data_into_owned_vec() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf[1] = 12;
+        let copy = buf.to_vec();
+        buf[0] = 4;
+        assert_eq!(vec![0, 12], copy);
+        assert_eq!(&[4, 12], buf.data());
+    }
+
+    #[test]
+    fn should_reflect_writes_through_mutable_views_in_data() {
+        let mut buf = Buffer::<u8>::padded(2, 2).unwrap();
+        {
+            let mut views = buf.views_mut();
+            assert_eq!(views[1].len(), 1);
+            views[1][0] = 7;
+        }
+        assert_eq!(&[0,0, 7,0], buf.data());
+    }
+
+    #[test]
+    fn should_iterate_entry_bytes_without_padding() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        buf.fill_bytes(0xff);
+        for (i, bytes) in buf.byte_entries_mut().enumerate() {
+            bytes.copy_from_slice(&[i as u8; 4]);
+        }
+        let entries: Vec<&[u8]> = buf.byte_entries().collect();
+        assert_eq!(entries, vec![&[0u8; 4][..], &[1u8; 4][..], &[2u8; 4][..]]);
+        assert_eq!(&buf.data()[4..8], &[0xff; 4]);
+    }
+
+    #[test]
+    fn should_view_unpadded_entries_as_typed_slice() {
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.as_mut_slice().unwrap()[2] = 9;
+        assert_eq!(Some(&[0, 0, 9][..]), buf.as_slice());
+        assert_eq!(&[0,0,0,0, 0,0,0,0, 9,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_not_view_padded_entries_as_typed_slice() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        assert!(buf.as_slice().is_none());
+        assert!(buf.as_mut_slice().is_none());
+    }
+
+    #[test]
+    fn should_place_indexed_data_propertly_in_second_slot() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf[1] = 12;
+        assert_eq!(buf[1], 12);
+        assert_eq!(&[0, 12], buf.data());
+    }
+
+    #[test]
+    fn should_place_indexed_data_propertly_in_second_slot_with_padding() {
+        let mut buf = Buffer::<u8>::padded(2, 4).unwrap();
+        buf[1] = 12;
+        assert_eq!(&[0,0,0,0, 12,0,0,0], buf.data());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_indexing_past_cap() {
+        let buf = Buffer::<u8>::new(2).unwrap();
+        let _ = &buf[2];
+    }
+
+    #[test]
+    fn should_expose_base_pointer_of_first_entry() {
+        let mut buf = Buffer::<Thing>::padded(2, 32).unwrap();
+        assert_eq!(buf.as_byte_ptr(), &buf[0] as *const Thing as *const u8);
+        assert_eq!(buf.as_ptr(), &buf[0] as *const Thing);
+        assert_eq!(buf.as_mut_ptr() as *const Thing, buf.as_ptr());
+    }
+
+    #[test]
+    fn should_offset_second_entry_by_padded_size() {
+        let mut buf = Buffer::<Thing>::padded(2, 32).unwrap();
+        assert_eq!(buf.byte_offset(0), 0);
+        assert_eq!(buf.byte_offset(1), buf.padded_size());
+        assert_eq!(buf.entry_ptr(1) as usize - buf.entry_ptr(0) as usize, 32);
+        assert_eq!(buf.entry_ptr(1) as *const u8, buf.as_byte_ptr().wrapping_add(32));
+        assert_eq!(buf.entry_ptr_mut(1) as *const Thing, buf.entry_ptr(1));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn should_reject_entry_ptr_past_the_last_slot_in_debug_builds() {
+        let buf = Buffer::<u32>::new(2).unwrap();
+        buf.entry_ptr(2);
+    }
+
+    #[test]
+    fn should_chunk_contiguous_entries() {
+        let mut buf = Buffer::<u32>::new(5).unwrap();
+        for (i, chunk) in buf.chunks_mut(2).unwrap().enumerate() {
+            for entry in chunk {
+                *entry = i as u32;
+            }
+        }
+        let lens: Vec<usize> = buf.chunks(2).unwrap().map(|chunk| chunk.len()).collect();
+        assert_eq!(lens, vec![2, 2, 1]);
+        assert_eq!(Some(&[0, 0, 1, 1, 2][..]), buf.as_slice());
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk size must be non zero")]
+    fn should_reject_zero_chunk_size_for_padded_entries() {
+        let mut buf = Buffer::<u32>::padded(4, 8).unwrap();
+        let _ = buf.chunks_mut(0);
+    }
+
+    #[test]
+    fn should_not_chunk_padded_entries() {
+        let mut buf = Buffer::<u32>::padded(5, 8).unwrap();
+        assert!(buf.chunks(2).is_none());
+        assert!(buf.chunks_mut(2).is_none());
+    }
+
+    #[test]
+    fn should_split_contiguous_entries_into_disjoint_halves() {
+        let mut buf = Buffer::<u8>::new(4).unwrap();
+        {
+            let (left, right) = buf.split_at_mut(1).unwrap();
+            assert_eq!((left.len(), right.len()), (1, 3));
+            left[0] = 1;
+            right[2] = 4;
+        }
+        assert_eq!(&[1, 0, 0, 4], buf.data());
+    }
+
+    #[test]
+    fn should_split_padded_entries_into_disjoint_views() {
+        let mut buf = Buffer::<u8>::padded(4, 2).unwrap();
+        assert!(buf.split_at_mut(2).is_none());
+        {
+            let (mut left, mut right) = buf.split_views_mut(2);
+            assert_eq!((left.cap(), right.cap()), (2, 2));
+            left[1] = 2;
+            right[0] = 3;
+            *right.get_mut(1).unwrap() = 4;
+            assert!(right.get(2).is_none());
+        }
+        assert_eq!(&[0,0, 2,0, 3,0, 4,0], buf.data());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_splitting_past_cap() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf.split_views_mut(3);
+    }
+
+    #[test]
+    fn should_window_over_slot_range() {
+        let mut buf = Buffer::<u32>::padded(10, 8).unwrap();
+        buf.fill_with(|i| i as u32);
+        let window = buf.window(2..5);
+        assert_eq!(window.cap(), 3);
+        assert_eq!(window[0], 2);
+        assert_eq!(window.get(0), buf.get(2));
+        assert!(window.get(3).is_none());
+        assert_eq!(window.iter().cloned().collect::<Vec<u32>>(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn should_write_through_mutable_window() {
+        let mut buf = Buffer::<u32>::new(10).unwrap();
+        {
+            let mut window = buf.window_mut(2..5);
+            window[0] = 7;
+            for entry in window.iter_mut() {
+                *entry += 1;
+            }
+        }
+        assert_eq!(Some(&[0, 0, 8, 1, 1, 0, 0, 0, 0, 0][..]), buf.as_slice());
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_window_ends_past_cap() {
+        let buf = Buffer::<u32>::new(4).unwrap();
+        buf.window(2..5);
+    }
+
+    #[test]
+    fn should_get_entries_in_range() {
+        let mut buf = Buffer::<u32>::padded(2, 8).unwrap();
+        *buf.get_mut(1).unwrap() = 5;
+        assert_eq!(buf.get(0), Some(&0));
+        assert_eq!(buf.get(1), Some(&5));
+    }
+
+    #[test]
+    fn should_not_get_entry_at_cap() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        assert!(buf.get(2).is_none());
+        assert!(buf.get_mut(2).is_none());
+    }
+
+    #[test]
+    fn should_not_get_entry_far_out_of_range() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        assert!(buf.get(usize::MAX).is_none());
+        assert!(buf.get_mut(usize::MAX).is_none());
+    }
+
+    #[test]
+    fn should_sum_values_written_through_iter_mut() {
+        let mut buf = Buffer::<u32>::padded(4, 8).unwrap();
+        for (i, entry) in buf.iter_mut().enumerate() {
+            *entry = i as u32 + 1;
+        }
+        assert_eq!(buf.iter().len(), 4);
+        assert_eq!(buf.iter().sum::<u32>(), 10);
+    }
+
+    #[test]
+    fn should_iterate_buffer_references_in_for_loops() {
+        let mut buf = Buffer::<u8>::new(3).unwrap();
+        for entry in &mut buf {
+            *entry += 2;
+        }
+        let mut total = 0;
+        for entry in &buf {
+            total += *entry;
+        }
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn should_zero_written_slots() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf[0] = 3;
+        buf[1] = 12;
+        buf.zero();
+        assert_eq!(&[0, 0], buf.data());
+    }
+
+    #[test]
+    fn should_fill_every_slot_with_constant() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        buf.fill(7);
+        assert_eq!(buf.iter().cloned().collect::<Vec<u32>>(), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn should_fill_every_slot_with_computed_value() {
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.fill_with(|i| i as u32);
+        assert_eq!(Some(&[0, 1, 2][..]), buf.as_slice());
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn should_cast_packed_bytes_to_pod_slice() {
+        let mut buf = Buffer::<[u8; 4]>::aligned(2, 4).unwrap();
+        buf[0] = 1u32.to_ne_bytes();
+        buf[1] = 0xdead_beefu32.to_ne_bytes();
+        assert_eq!(buf.as_pod_slice::<u32>().ok(), Some(&[1, 0xdead_beef][..]));
+        assert_eq!(buf.as_pod_slice::<u16>().map(|s| s.len()).ok(), Some(4));
+        match buf.as_pod_slice::<[u8; 3]>() {
+            Err(Error::IncompatibleCast) => {},
+            other => panic!("expected IncompatibleCast, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn should_reject_pod_slice_over_padded_entries() {
+        let buf = Buffer::<u32>::with_padding(2, Padding::Padded(8)).unwrap();
+        match buf.as_pod_slice::<u32>() {
+            Err(Error::IncompatibleCast) => {},
+            other => panic!("expected IncompatibleCast, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn should_build_buffer_from_pod_values() {
+        let buf = Buffer::<[u8; 4]>::from_pod(&[1u32, 2]).unwrap();
+        assert_eq!(buf.cap(), 2);
+        assert_eq!(buf[1], 2u32.to_ne_bytes());
+        assert!(Buffer::<u32>::from_pod(&[1u16, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn should_align_cache_aligned_base_to_a_cache_line() {
+        fn check<T>() {
+            let buf = Buffer::<T>::cache_aligned(3).unwrap();
+            assert_eq!((buf.as_byte_ptr() as usize) % cache_line_size(), 0);
+            assert_eq!(buf.alignment(), max(mem::align_of::<T>(), cache_line_size()));
+            assert!(buf.is_cache_aligned());
+        }
+        check::<u8>();
+        check::<u16>();
+        check::<u64>();
+        check::<[u8; 3]>();
+        check::<[u64; 9]>();
+    }
+
+    #[test]
+    fn should_isolate_each_entry_on_whole_cache_lines() {
+        #[allow(dead_code)]
+        struct Record { bytes: [u8; 100] }
+        let line = cache_line_size();
+        let buf = Buffer::<Record>::with_padding(3, Padding::Isolated).unwrap();
+        assert_eq!(buf.padded_size(), 100usize.div_ceil(line) * line);
+        if line == 64 {
+            assert_eq!(buf.padded_size(), 128);
+        }
+        assert!(buf.is_cache_aligned());
+        let lines = (0..buf.cap())
+            .map(|i| {
+                let start = buf.entry_ptr(i) as usize;
+                (start / line, (start + buf.size() - 1) / line)
+            })
+            .collect::<Vec<_>>();
+        for pair in lines.windows(2) {
+            assert!(pair[0].1 < pair[1].0, "slots share a cache line: {:?}", lines);
+        }
+        let units = Buffer::<()>::with_padding(2, Padding::Isolated).unwrap();
+        assert_eq!(units.padded_size(), line);
+        assert!(units.is_cache_aligned());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn should_allocate_page_aligned_buffer_with_platform_allocator() {
+        let mut buf = Buffer::<u64>::aligned(3, 4096).unwrap();
+        assert!(matches!(buf.backing, Backing::Memalign));
+        assert_eq!(buf.as_byte_ptr() as usize % 4096, 0);
+        assert_eq!((buf.alignment(), buf.padded_size()), (4096, 4096));
+        assert!(buf.data().iter().all(|b| *b == 0));
+        buf[2] = 42;
+        buf.grow(4).unwrap(); // Moves to a `Global` allocation, freeing the aligned one with `free`
+        assert!(matches!(buf.backing, Backing::Heap));
+        assert_eq!((buf[2], buf.as_byte_ptr() as usize % 4096), (42, 0));
+        let small = Buffer::<u64>::aligned(3, 64).unwrap();
+        assert!(matches!(small.backing, Backing::Heap));
+    }
+
+    #[test]
+    fn should_zero_padding_keeping_entries() {
+        let mut buf = Buffer::<u32>::padded(2, 8).unwrap();
+        buf[0] = 0xAAAA_AAAA;
+        buf[1] = 0x1234_5678;
+        let raw = buf.as_mut_ptr() as *mut u8;
+        unsafe {
+            ptr::write_bytes(raw.add(4), 0xCC, 4);
+            ptr::write_bytes(raw.add(12), 0xCC, 4);
+        }
+        buf.zero_padding();
+        assert_eq!((buf[0], buf[1]), (0xAAAA_AAAA, 0x1234_5678));
+        assert!(buf.data()[4..8].iter().chain(&buf.data()[12..]).all(|b| *b == 0));
+        let mut packed = Buffer::<u32>::new(2).unwrap();
+        packed.fill(u32::MAX);
+        packed.zero_padding();
+        assert!(packed.data().iter().all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn should_fill_through_uninit_slice() {
+        let mut buf = Buffer::<u64>::new(3).unwrap();
+        for (i, slot) in buf.as_uninit_slice_mut().unwrap().iter_mut().enumerate() {
+            slot.write(i as u64 * 10);
+        }
+        assert_eq!((buf.get(0), buf.get(1), buf.get(2)), (Some(&0), Some(&10), Some(&20)));
+        assert!(Buffer::<u64>::padded(3, 16).unwrap().as_uninit_slice_mut().is_none());
+    }
+
+    #[test]
+    fn should_copy_entry_bytes_in_and_out() {
+        let mut buf = Buffer::<[u8; 3]>::padded(3, 8).unwrap();
+        buf.write_entry_bytes(1, &[1, 2, 3]).unwrap();
+        buf.write_entry_bytes(2, &[9]).unwrap();
+        assert_eq!((buf[1], buf[2]), ([1, 2, 3], [9, 0, 0]));
+        assert!(buf.data()[8..16].iter().skip(3).all(|b| *b == 0));
+        let mut out = [0xFF; 4];
+        buf.read_entry_bytes(1, &mut out).unwrap();
+        assert_eq!(out, [1, 2, 3, 0xFF]);
+        match buf.read_entry_bytes(1, &mut [0; 2]) {
+            Err(Error::SliceTooShort) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match buf.write_entry_bytes(0, &[0; 4]) {
+            Err(Error::CapacityExceeded) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match buf.read_entry_bytes(3, &mut out) {
+            Err(Error::IndexOutOfBounds) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_yield_only_occupied_indices() {
+        let mut buf = Buffer::<u32>::new(130).unwrap();
+        assert_eq!(buf.occupied_indices().count(), 0);
+        buf.set_occupied(3, false);
+        assert!(buf.occupancy.is_empty());
+        for &index in &[129, 3, 64, 0, 63] {
+            buf.set_occupied(index, true);
+        }
+        buf.set_occupied(63, false);
+        assert!(buf.is_occupied(64) && !buf.is_occupied(63) && !buf.is_occupied(500));
+        assert_eq!(buf.occupied_indices().collect::<Vec<_>>(), vec![0, 3, 64, 129]);
+        buf.shrink_to(100).unwrap();
+        assert_eq!(buf.occupied_indices().collect::<Vec<_>>(), vec![0, 3, 64]);
+        buf.grow(200).unwrap();
+        assert!(!buf.is_occupied(129));
+    }
+
+    // Counts its drops so tests can check each value is dropped exactly once
+    struct Dropper<'c>(&'c Cell<usize>);
+
+    impl<'c> Drop for Dropper<'c> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn should_drop_each_initialized_slot_once_when_tracked() {
+        let drops = Cell::new(0);
+        {
+            let mut buf = Buffer::<Dropper>::tracked(8).unwrap();
+            assert!(buf.is_tracked());
+            buf.insert(0, Dropper(&drops));
+            buf.insert(2, Dropper(&drops));
+            buf.insert(2, Dropper(&drops));
+            assert_eq!(drops.get(), 1);
+            let taken = buf.take(0);
+            assert!(taken.is_some() && buf.take(0).is_none() && buf.take(1).is_none());
+            drop(taken);
+            assert_eq!(drops.get(), 2);
+            buf.insert(5, Dropper(&drops));
+            buf.insert(7, Dropper(&drops));
+            buf.swap(5, 1);
+            assert!(buf.take(5).is_none());
+            buf.shrink_to(4).unwrap();
+            assert_eq!(drops.get(), 3);
+        }
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn should_split_off_into_independently_owned_halves() {
+        let mut head = Buffer::from_iter_with_cap(vec![1u32, 2, 3, 4], 4).unwrap();
+        let tail = head.split_off(2).unwrap();
+        assert_eq!((head.cap(), tail.cap()), (2, 2));
+        assert_eq!((head.to_contiguous(), tail.to_contiguous()), (vec![1, 2], vec![3, 4]));
+        assert!(head.data_size() == 8 && tail.data_size() == 8);
+        drop(head);
+        assert_eq!(tail[1], 4);
+        let mut padded = Buffer::<u32>::padded(3, 8).unwrap();
+        padded[2] = 9;
+        let rest = padded.split_off(1).unwrap();
+        assert_eq!((rest.padded_size(), rest[1]), (8, 9));
+        assert_eq!(padded.split_off(1).unwrap().cap(), 0);
+        match padded.split_off(2) {
+            Err(Error::IndexOutOfBounds) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_split_tracked_values_without_dropping_them() {
+        let drops = Cell::new(0);
+        let mut head = Buffer::<Dropper>::tracked(4).unwrap();
+        head.fill_with(|_| Dropper(&drops));
+        let tail = head.split_off(1).unwrap();
+        assert!(tail.is_tracked());
+        assert_eq!(drops.get(), 0);
+        drop(tail);
+        assert_eq!(drops.get(), 3);
+        drop(head);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_keep_split_off_tail_of_locked_buffer_locked() {
+        let mut head = match Buffer::<u64>::secure_locked(4) {
+            Ok(buf) => buf,
+            Err(Error::LockFailed) => return,
+            Err(error) => panic!("unexpected error: {:?}", error),
+        };
+        match head.split_off(1) {
+            Ok(tail) => assert!(tail.locked && tail.is_secure()),
+            Err(Error::LockFailed) => {},
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn should_keep_every_value_when_split_off_cannot_lock_the_shrunk_buffer() {
+        let mut head = Buffer::<String>::tracked(4).unwrap();
+        head.fill_with(|i| i.to_string());
+        match head.lock() {
+            Ok(()) => head.locked = true,
+            Err(Error::LockFailed) => return,
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+        // The tail still gets locked, the shrunk allocation does not
+        LOCKS_LEFT.with(|left| left.set(Some(1)));
+        let result = head.split_off(2);
+        LOCKS_LEFT.with(|left| left.set(None));
+        match result {
+            Err(Error::LockFailed) => {},
+            result => panic!("unexpected result: {:?}", result.map(|tail| tail.cap())),
+        }
+        assert!(head.locked);
+        assert_eq!(head.cap(), 4);
+        for i in (0..4).rev() {
+            assert_eq!(head.take(i), Some(i.to_string()));
+        }
+        let tail = head.split_off(2).unwrap();
+        assert_eq!((head.cap(), tail.cap()), (2, 2));
+    }
+
+    #[test]
+    fn should_split_tracked_buffer_at_start_and_across_bit_words() {
+        let drops = Cell::new(0);
+        let mut head = Buffer::<Dropper>::tracked(4).unwrap();
+        head.fill_with(|_| Dropper(&drops));
+        let tail = head.split_off(0).unwrap();
+        assert_eq!((head.cap(), tail.cap(), drops.get()), (0, 4, 0));
+        drop(tail);
+        assert_eq!(drops.get(), 4);
+        let mut head = Buffer::<Dropper>::tracked(100).unwrap();
+        head.insert(5, Dropper(&drops));
+        head.insert(80, Dropper(&drops));
+        let mut tail = head.split_off(10).unwrap();
+        assert_eq!(drops.get(), 4);
+        assert!(tail.take(69).is_none());
+        drop(head);
+        assert_eq!(drops.get(), 5);
+        assert!(tail.take(70).is_some());
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn should_track_pushed_values_until_popped() {
+        let drops = Cell::new(0);
+        {
+            let mut buf = Buffer::<Dropper>::tracked(3).unwrap();
+            for _ in 0..3 {
+                assert!(buf.push(Dropper(&drops)).is_ok());
+            }
+            drop(buf.pop());
+            assert_eq!(drops.get(), 1);
+            buf.zero();
+            assert_eq!(drops.get(), 3);
+            buf.clear();
+            assert!(buf.push(Dropper(&drops)).is_ok());
+        }
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn should_leave_untracked_values_undropped() {
+        let drops = Cell::new(0);
+        {
+            let mut buf = Buffer::<Dropper>::new(2).unwrap();
+            assert!(!buf.is_tracked());
+            buf.insert(0, Dropper(&drops));
+            assert!(buf.take(0).is_none());
+        }
+        assert_eq!(drops.get(), 0);
+    }
+
+    #[test]
+    fn should_expose_whole_region_with_its_alignment() {
+        let buf = Buffer::<u16>::aligned(5, 32).unwrap();
+        let (bytes, align) = buf.as_aligned_bytes();
+        assert_eq!(bytes.len(), buf.data_size());
+        assert_eq!(align, buf.layout.align());
+        assert_eq!(align, 32);
+        assert_eq!(bytes.as_ptr() as usize % align, 0);
+    }
+
+    #[test]
+    fn should_checksum_entries_regardless_of_padding() {
+        let packed = Buffer::from_iter_with_cap(vec![1u32, 2, 3], 3).unwrap();
+        let mut padded = Buffer::<u32>::padded(3, 16).unwrap();
+        padded.fill_bytes(0xCC);
+        for (i, value) in packed.iter().enumerate() {
+            padded[i] = *value;
+        }
+        let checksum = packed.checksum();
+        assert_eq!(padded.checksum(), checksum);
+        assert!(padded.verify(checksum));
+        padded[1] = 5;
+        assert!(!padded.verify(checksum));
+        assert_ne!(Buffer::<u32>::new(3).unwrap().checksum(), checksum);
+    }
+
+    #[test]
+    fn should_hexdump_data_like_xxd() {
+        let mut buf = Buffer::<u32>::new(5).unwrap();
+        buf[0] = 0x0403_0201;
+        buf.data_mut()[16..].copy_from_slice(b"Abc~");
+        let dump = buf.hexdump();
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let first = if cfg!(target_endian = "little") { "00000000: 0102 0304" } else { "00000000: 0403 0201" };
+        assert!(lines[0].starts_with(first), "{}", dump);
+        assert!(lines[0].ends_with("  ................"), "{}", dump);
+        assert!(lines[1].starts_with("00000010: 4162 637e "), "{}", dump);
+        assert!(lines[1].ends_with("  Abc~"), "{}", dump);
+        assert_eq!(lines[1].len(), lines[0].len() - 12);
+        let mut out = String::new();
+        buf.hexdump_to(&mut out).unwrap();
+        assert_eq!(out, dump);
+    }
+
+    #[test]
+    fn should_repad_keeping_entries() {
+        let mut buf = Buffer::from_iter_with_cap(vec![7u32, 8, 9], 3).unwrap();
+        buf.repad(Padding::CacheAligned).unwrap();
+        let line = cache_line_size();
+        assert_eq!((buf.cap(), buf.padded_size(), buf.data_size()), (3, line, 3 * line));
+        assert_eq!(buf.padding_mode(), Padding::CacheAligned);
+        assert_eq!(buf.to_contiguous(), vec![7, 8, 9]);
+        assert_eq!(&buf.data()[line..line + 4], &8u32.to_ne_bytes());
+        buf.repad(Padding::None).unwrap();
+        assert_eq!(Some(&[7, 8, 9][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_reject_misaligned_repad_leaving_buffer_intact() {
+        let mut buf = Buffer::from_iter_with_cap(vec![1u64, 2], 2).unwrap();
+        assert!(buf.repad(Padding::Padded(12)).is_err());
+        assert_eq!((buf.padded_size(), buf.padding_mode()), (8, Padding::None));
+        assert_eq!(Some(&[1, 2][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_push_and_pop_like_a_fixed_stack() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(buf.pop(), None);
+        buf.push(1).unwrap();
+        buf.push(2).unwrap();
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf.pop(), Some(2));
+        buf.push(3).unwrap();
+        buf.push(4).unwrap();
+        assert!(buf.is_full());
+        assert_eq!(buf.push(5), Err(5));
+        assert_eq!(buf.to_contiguous(), vec![1, 3, 4]);
+        buf.shrink_to(2).unwrap();
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf.clone().len(), 2);
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.cap(), 2);
+    }
+
+    #[test]
+    fn should_write_big_endian_bytes_at_slot_offset() {
+        let mut buf = Buffer::<u32>::padded(2, 8).unwrap();
+        buf.write_u32_be(1, 0x0102_0304);
+        buf.write_u16_le(0, 0x0a0b);
+        assert_eq!(buf.data(), &[0x0b, 0x0a, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0]);
+        assert_eq!(buf.read_u32_be(1), 0x0102_0304);
+        assert_eq!(buf.read_u32_le(1), 0x0403_0201);
+        assert_eq!(buf.read_u16_le(0), 0x0a0b);
+        buf.write_u64_le(0, u64::MAX);
+        assert_eq!(buf.read_u64_be(0), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_endian_write_overruns_allocation() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        buf.write_u64_be(1, 1);
+    }
+
+    #[test]
+    fn should_fill_every_byte_with_pattern() {
+        let mut buf = Buffer::<u16>::padded(3, 6).unwrap();
+        buf.fill_bytes(0xAB);
+        assert_eq!(buf.data().len(), 18);
+        assert!(buf.data().iter().all(|&byte| byte == 0xAB));
+        assert_eq!(buf[2], 0xABAB);
+    }
+
+    #[test]
+    fn should_copy_from_slice_that_fits_exactly() {
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.copy_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(Some(&[1, 2, 3][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_copy_from_slice_into_leading_slots() {
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.copy_from_slice(&[1, 2]).unwrap();
+        assert_eq!(Some(&[1, 2, 0][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_reject_copy_from_slice_over_capacity() {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        match buf.copy_from_slice(&[1, 2, 3]) {
+            Err(Error::CapacityExceeded) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(Some(&[0, 0][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_copy_from_slice_at_padded_stride() {
+        let mut buf = Buffer::<u8>::padded(2, 4).unwrap();
+        buf.copy_from_slice(&[5, 6]).unwrap();
+        assert_eq!(&[5,0,0,0, 6,0,0,0], buf.data());
+    }
+
+    #[test]
+    fn should_reserve_the_bytes_construction_allocates() {
+        assert_eq!(reserve_bytes::<u32>(3, 8).unwrap(), Buffer::<u32>::padded(3, 8).unwrap().data_size());
+        assert_eq!(reserve_bytes::<u64>(5, 0).unwrap(), Buffer::<u64>::try_new(5).unwrap().data_size());
+        assert_eq!(reserve_bytes::<u8>(0, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn should_report_overflow_when_reserving_huge_buffers() {
+        match reserve_bytes::<u64>(usize::MAX, 8) {
+            Err(Error::BufferSizeOverflow) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_adopt_vec_contents() {
+        let buf = Buffer::try_from(vec![1u32, 2, 3]).unwrap();
+        assert_eq!(buf.cap(), 3);
+        assert_eq!((buf[0], buf[1], buf[2]), (1, 2, 3));
+        let empty = Buffer::<u32>::try_from(Vec::new()).unwrap();
+        assert_eq!(empty.cap(), 0);
+    }
+
+    #[test]
+    fn should_collect_exactly_cap_items() {
+        let buf = Buffer::from_iter_with_cap(1u32..4, 3).unwrap();
+        assert_eq!(Some(&[1, 2, 3][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_collect_fewer_items_leaving_slots_zeroed() {
+        let buf = Buffer::from_iter_with_cap(vec![7u32], 3).unwrap();
+        assert_eq!(Some(&[7, 0, 0][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_reject_collecting_more_than_cap_items() {
+        match Buffer::from_iter_with_cap(0u32..4, 3) {
+            Err(Error::CapacityExceeded) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_describe_layout_for_ffi() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        buf[2] = 9;
+        let ffi = buf.as_ffi();
+        assert_eq!(ffi.ptr as *const u8, buf.as_byte_ptr());
+        assert_eq!((ffi.len, ffi.stride, ffi.count), (buf.size(), buf.padded_size(), buf.cap()));
+        assert_eq!(unsafe { *(ffi.ptr.add(2 * ffi.stride) as *const u32) }, 9);
+    }
+
+    #[test]
+    fn should_compact_padded_entries() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        assert!(!buf.is_contiguous());
+        buf.data_mut().iter_mut().for_each(|byte| *byte = 0xee);
+        buf.copy_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(buf.to_contiguous(), vec![1, 2, 3]);
+        assert!(Buffer::<u32>::new(3).unwrap().is_contiguous());
+    }
+
+    #[test]
+    fn should_move_padded_entries_into_vec() {
+        let mut buf = Buffer::<u32>::padded(3, 8).unwrap();
+        buf.data_mut().iter_mut().for_each(|byte| *byte = 0xff); // Padding bytes must not leak in
+        buf.fill_with(|i| i as u32 + 10);
+        assert_eq!(buf.into_vec(), vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn should_compare_equal_when_filled_alike() {
+        let mut a = Buffer::<u32>::new(3).unwrap();
+        let mut b = Buffer::<u32>::padded(3, 8).unwrap();
+        a.fill(4);
+        b.fill(4);
+        assert_eq!(a, b);
+        b[1] = 5;
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn should_compare_unequal_when_capacities_differ() {
+        let a = Buffer::<u32>::new(3).unwrap();
+        let b = Buffer::<u32>::new(4).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn should_hash_entries_regardless_of_padding() {
+        use std::collections::hash_map::DefaultHasher;
+        fn hash_of(buf: &Buffer<u32>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            buf.hash(&mut hasher);
+            hasher.finish()
+        }
+        let a = Buffer::from_iter_with_cap(1u32..4, 3).unwrap();
+        let mut b = Buffer::<u32>::padded(3, 16).unwrap();
+        b.copy_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(hash_of(&a), hash_of(&b));
+        b[2] = 4;
+        assert_ne!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn should_debug_format_entries() {
+        let buf = Buffer::from_iter_with_cap(vec![3u32, 1, 4], 3).unwrap();
+        assert_eq!(format!("{:?}", buf), "Buffer { cap: 3, size: 4, padded_size: 4, entries: [3, 1, 4] }");
+    }
+
+    #[test]
+    fn should_truncate_debug_entries_of_large_buffers() {
+        let buf = Buffer::from_iter_with_cap(0u32..100, 100).unwrap();
+        let debug = format!("{:?}", buf);
+        assert!(debug.ends_with("entries: [0, 1, 2, 3, 4, 5, 6, 7, ...] }"), "{}", debug);
+    }
+
+    #[test]
+    fn should_copy_from_buffer_with_same_layout() {
+        let src = Buffer::from_iter_with_cap(1u64..5, 4).unwrap();
+        let mut dst = Buffer::<u64>::new(4).unwrap();
+        dst.copy_from(&src).unwrap();
+        assert_eq!(dst, src);
+    }
+
+    #[test]
+    fn should_reject_copy_from_buffer_with_other_layout() {
+        let src = Buffer::<u64>::padded(4, 16).unwrap();
+        let mut dst = Buffer::<u64>::new(4).unwrap();
+        match dst.copy_from(&src) {
+            Err(Error::LayoutMismatch) => {},
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let src = Buffer::<u64>::new(3).unwrap();
+        assert!(dst.copy_from(&src).is_err());
+    }
+
+    #[test]
+    fn should_prefetch_without_changing_contents() {
+        let mut buf = Buffer::<u64>::cache_aligned(4).unwrap();
+        buf.fill_with(|i| i as u64 + 1);
+        let before = buf.to_vec();
+        for &locality in &[Locality::T0, Locality::T1, Locality::T2, Locality::Nta] {
+            for i in 0..6 { // Out of bounds indices are ignored
+                buf.prefetch(i, locality);
+            }
+        }
+        assert_eq!(buf.to_vec(), before);
+    }
+
+    #[test]
+    fn should_swap_struct_slots() {
+        let mut buf = Buffer::<Thing>::new(2).unwrap();
+        buf[0].value1 = 36;
+        buf[1].value2 = 12;
+        buf.swap(0, 1);
+        assert_eq!(&[0,0,0,0,0,0,0,0, 12,0,0,0,0,0,0,0, 36,0,0,0,0,0,0,0, 0,0,0,0,0,0,0,0], buf.data());
+        buf.swap(1, 1);
+        assert_eq!(buf[1].value1, 36);
+    }
+
+    #[test]
+    fn should_binary_search_sorted_entries() {
+        let buf = Buffer::from_iter_with_cap(vec![1u32, 3, 5, 7, 9], 5).unwrap();
+        assert_eq!(buf.binary_search(&1), Ok(0));
+        assert_eq!(buf.binary_search(&7), Ok(3));
+        assert_eq!(buf.binary_search(&0), Err(0));
+        assert_eq!(buf.binary_search(&4), Err(2));
+        assert_eq!(buf.binary_search(&10), Err(5));
+    }
+
+    #[test]
+    fn should_binary_search_padded_entries_by_comparator() {
+        let mut buf = Buffer::<u32>::padded(4, 16).unwrap();
+        buf.copy_from_slice(&[40, 30, 20, 10]).unwrap(); // Sorted descending
+        assert_eq!(buf.binary_search_by(|entry| 20.cmp(entry)), Ok(2));
+        assert_eq!(buf.binary_search_by(|entry| 25.cmp(entry)), Err(2));
+        assert_eq!(Buffer::<u32>::new(0).unwrap().binary_search(&1), Err(0));
+    }
+
+    #[test]
+    fn should_sort_scrambled_entries() {
+        let mut buf = Buffer::from_iter_with_cap(vec![5u32, 1, 4, 2, 3, 2], 6).unwrap();
+        buf.sort();
+        assert_eq!(Some(&[1, 2, 2, 3, 4, 5][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_sort_padded_entries_without_moving_padding() {
+        let mut buf = Buffer::<u32>::padded(7, 8).unwrap();
+        for (i, &value) in [9u32, 3, 7, 1, 8, 2, 5].iter().enumerate() {
+            buf[i] = value;
+            unsafe {
+                ptr::write(buf.entry_ptr_mut(i).add(1), 100 + i as u32);
+            }
+        }
+        buf.sort_by(|a, b| b.cmp(a));
+        assert_eq!(buf.iter().copied().collect::<Vec<u32>>(), vec![9, 8, 7, 5, 3, 2, 1]);
+        for i in 0..7 {
+            assert_eq!(unsafe { *buf.entry_ptr(i).add(1) }, 100 + i as u32);
+        }
+    }
+
+    #[test]
+    fn should_reverse_entries() {
+        let mut buf = Buffer::from_iter_with_cap(1u32..6, 5).unwrap();
+        buf.reverse();
+        assert_eq!(Some(&[5, 4, 3, 2, 1][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_reverse_without_moving_padding() {
+        let mut buf = Buffer::<u16>::padded(3, 4).unwrap();
+        buf.data_mut().copy_from_slice(&[1, 0, 7, 7, 2, 0, 8, 8, 3, 0, 9, 9]);
+        buf.reverse();
+        assert_eq!(&[3, 0, 7, 7, 2, 0, 8, 8, 1, 0, 9, 9], buf.data());
+    }
+
+    #[test]
+    fn should_rotate_left() {
+        let mut buf = Buffer::<u8>::new(4).unwrap();
+        buf.copy_from_slice(&[1, 2, 3, 4]).unwrap();
+        buf.rotate_left(1);
+        assert_eq!(Some(&[2, 3, 4, 1][..]), buf.as_slice());
+        buf.rotate_left(6);
+        assert_eq!(Some(&[4, 1, 2, 3][..]), buf.as_slice());
+    }
+
+    #[test]
+    fn should_rotate_right_keeping_padding_in_place() {
+        let mut buf = Buffer::<u8>::padded(4, 2).unwrap();
+        buf.data_mut().copy_from_slice(&[1, 9, 2, 9, 3, 9, 4, 9]);
+        buf.rotate_right(1);
+        assert_eq!(&[4, 9, 1, 9, 2, 9, 3, 9], buf.data());
+        buf.rotate_right(4);
+        assert_eq!(&[4, 9, 1, 9, 2, 9, 3, 9], buf.data());
+    }
+
+    #[test]
+    fn should_rotate_empty_buffer() {
+        let mut buf = Buffer::<u8>::new(0).unwrap();
+        buf.rotate_left(3);
+        buf.rotate_right(3);
+        assert_eq!(buf.cap(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_when_swapping_past_cap() {
+        let mut buf = Buffer::<u8>::new(2).unwrap();
+        buf.swap(0, 2);
+    }
+
+    #[test]
+    fn should_keep_pow2_stride_for_u8() {
+        let buf = Buffer::<u8>::pow2(2).unwrap();
+        assert_eq!(buf.padded_size(), 1);
+    }
+
+    #[test]
+    fn should_keep_pow2_stride_for_struct_thing() {
+        let buf = Buffer::<Thing>::pow2(2).unwrap();
+        assert_eq!(buf.padded_size(), 16);
+    }
+
+    #[test]
+    fn should_round_pow2_stride_up_for_odd_sized_struct() {
+        let buf = Buffer::<[u8; 17]>::pow2(2).unwrap();
+        assert_eq!(buf.size(), 17);
+        assert_eq!(buf.padded_size(), 32);
+        assert_eq!(buf.data_size(), 64);
+    }
+
+    #[test]
+    fn should_give_zero_sized_types_a_pow2_stride_of_one() {
+        let buf = Buffer::<()>::pow2(2).unwrap();
+        assert_eq!(buf.padded_size(), 1);
+    }
+
+    #[test]
+    fn should_pad_struct_thing_up_to_cache_line() {
+        let buf = Buffer::<Thing>::cache_aligned(2).unwrap();
+        assert_eq!(buf.size(), 16);
+        assert_eq!(buf.padded_size(), cache_line_size());
+        assert_eq!(buf.padded_size() % cache_line_size(), 0);
+    }
+
+    #[test]
+    fn should_not_over_pad_naturally_cache_aligned_entries() {
+        let buf = Buffer::<[u8; CACHE_LINE_SIZE * 2]>::cache_aligned(2).unwrap();
+        assert_eq!(buf.padded_size(), cache_line_size() * 2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,41 @@
+use core::sync::atomic::{ AtomicUsize, Ordering };
+
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BUFFERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Snapshot of the crate wide allocation counters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocStats {
+    pub allocated_bytes: usize,
+    pub live_buffers: usize,
+}
+
+impl AllocStats {
+    /// The two counters are read separately, so a snapshot taken while other threads allocate may mix them
+    pub fn current() -> Self {
+        AllocStats {
+            allocated_bytes: allocated_bytes(),
+            live_buffers: live_buffers(),
+        }
+    }
+}
+
+/// Bytes currently held by live buffers, empty buffers hold none
+pub fn allocated_bytes() -> usize {
+    ALLOCATED_BYTES.load(Ordering::Relaxed)
+}
+
+/// Buffers currently holding an allocation
+pub fn live_buffers() -> usize {
+    LIVE_BUFFERS.load(Ordering::Relaxed)
+}
+
+pub(crate) fn record_alloc(bytes: usize) {
+    ALLOCATED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    LIVE_BUFFERS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_release(bytes: usize) {
+    ALLOCATED_BYTES.fetch_sub(bytes, Ordering::Relaxed);
+    LIVE_BUFFERS.fetch_sub(1, Ordering::Relaxed);
+}
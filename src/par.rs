@@ -0,0 +1,229 @@
+use core::marker::PhantomData;
+
+use allocator_api2::alloc::Allocator;
+use rayon::iter::plumbing::{ bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer };
+use rayon::prelude::*;
+
+use { view, Buffer, BufferView, BufferViewMut, Iter, IterMut };
+
+/// Parallel iterator over references to every slot, split by slot ranges so padding never matters
+pub struct ParIter<'b, T: 'b> {
+    ptr: *mut u8,
+    cap: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b T>,
+}
+
+unsafe impl<'b, T: Sync + 'b> Send for ParIter<'b, T> {}
+
+/// Parallel iterator over mutable references to every slot
+pub struct ParIterMut<'b, T: 'b> {
+    ptr: *mut u8,
+    cap: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b mut T>,
+}
+
+unsafe impl<'b, T: Send + 'b> Send for ParIterMut<'b, T> {}
+
+// Raw base pointer shared by the tasks of `par_chunks`, each of which only touches its own slots
+#[derive(Clone, Copy)]
+struct Base(*mut u8);
+
+unsafe impl Send for Base {}
+unsafe impl Sync for Base {}
+
+impl <'a, T: 'a, A: Allocator> Buffer<'a, T, A> {
+    /// Views over consecutive runs of `n` slots, processed in parallel, the last may be shorter.
+    /// Unlike `chunks` this works for padded buffers too.
+    pub fn par_chunks(&self, n: usize) -> impl IndexedParallelIterator<Item = BufferView<'_, T>> + '_ where T: Sync {
+        assert!(n > 0, "chunk size must be non zero");
+        let (base, cap, size, padded_size) = (Base(self.ptr), self.cap, self.size, self.padded_size);
+        (0..cap.div_ceil(n)).into_par_iter().map(move |chunk| {
+            let start = chunk * n;
+            unsafe {
+                view::view(base.0.add(start * padded_size), n.min(cap - start), size, padded_size)
+            }
+        })
+    }
+
+    /// Mutable views over consecutive runs of `n` slots, each handed to exactly one task
+    pub fn par_chunks_mut(&mut self, n: usize) -> impl IndexedParallelIterator<Item = BufferViewMut<'_, T>> + '_ where T: Send {
+        assert!(n > 0, "chunk size must be non zero");
+        let (base, cap, size, padded_size) = (Base(self.ptr), self.cap, self.size, self.padded_size);
+        (0..cap.div_ceil(n)).into_par_iter().map(move |chunk| {
+            let start = chunk * n;
+            unsafe {
+                view::view_mut(base.0.add(start * padded_size), n.min(cap - start), size, padded_size)
+            }
+        })
+    }
+}
+
+impl <'b, 'a: 'b, T: Sync + 'a, A: Allocator> IntoParallelIterator for &'b Buffer<'a, T, A> {
+    type Iter = ParIter<'b, T>;
+    type Item = &'b T;
+
+    fn into_par_iter(self) -> ParIter<'b, T> {
+        ParIter {
+            ptr: self.ptr,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl <'b, 'a: 'b, T: Send + 'a, A: Allocator> IntoParallelIterator for &'b mut Buffer<'a, T, A> {
+    type Iter = ParIterMut<'b, T>;
+    type Item = &'b mut T;
+
+    fn into_par_iter(self) -> ParIterMut<'b, T> {
+        ParIterMut {
+            ptr: self.ptr,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl <'b, T: Sync + 'b> ParallelIterator for ParIter<'b, T> {
+    type Item = &'b T;
+
+    fn drive_unindexed<C: UnindexedConsumer<&'b T>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.cap)
+    }
+}
+
+impl <'b, T: Sync + 'b> IndexedParallelIterator for ParIter<'b, T> {
+    fn len(&self) -> usize {
+        self.cap
+    }
+
+    fn drive<C: Consumer<&'b T>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<&'b T>>(self, callback: CB) -> CB::Output {
+        callback.callback(self)
+    }
+}
+
+impl <'b, T: Sync + 'b> Producer for ParIter<'b, T> {
+    type Item = &'b T;
+    type IntoIter = Iter<'b, T>;
+
+    fn into_iter(self) -> Iter<'b, T> {
+        Iter {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let right = ParIter {
+            ptr: self.ptr.wrapping_add(index * self.padded_size),
+            cap: self.cap - index,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        };
+        (ParIter { cap: index, ..self }, right)
+    }
+}
+
+impl <'b, T: Send + 'b> ParallelIterator for ParIterMut<'b, T> {
+    type Item = &'b mut T;
+
+    fn drive_unindexed<C: UnindexedConsumer<&'b mut T>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.cap)
+    }
+}
+
+impl <'b, T: Send + 'b> IndexedParallelIterator for ParIterMut<'b, T> {
+    fn len(&self) -> usize {
+        self.cap
+    }
+
+    fn drive<C: Consumer<&'b mut T>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<&'b mut T>>(self, callback: CB) -> CB::Output {
+        callback.callback(self)
+    }
+}
+
+// Splits hand disjoint slot ranges to each side, so the mutable references never alias
+impl <'b, T: Send + 'b> Producer for ParIterMut<'b, T> {
+    type Item = &'b mut T;
+    type IntoIter = IterMut<'b, T>;
+
+    fn into_iter(self) -> IterMut<'b, T> {
+        IterMut {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let right = ParIterMut {
+            ptr: self.ptr.wrapping_add(index * self.padded_size),
+            cap: self.cap - index,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        };
+        (ParIterMut { cap: index, ..self }, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Padding;
+
+    #[test]
+    fn should_sum_in_parallel_like_sequentially() {
+        let mut buf = Buffer::<u64>::new(10_000).unwrap();
+        buf.fill_with(|i| i as u64 * 3);
+        let sequential: u64 = buf.iter().sum();
+        assert_eq!(buf.par_iter().sum::<u64>(), sequential);
+        buf.par_iter_mut().for_each(|entry| *entry += 1);
+        assert_eq!(buf.par_iter().sum::<u64>(), sequential + 10_000);
+    }
+
+    #[test]
+    fn should_iterate_padded_slots_in_parallel() {
+        let mut buf = Buffer::<u32>::with_padding(1_000, Padding::CacheAligned).unwrap();
+        buf.par_iter_mut().enumerate().for_each(|(i, entry)| *entry = i as u32);
+        assert!(buf.iter().enumerate().all(|(i, entry)| *entry == i as u32));
+        assert_eq!(buf.par_iter().rev().take(2).cloned().collect::<Vec<_>>(), vec![999, 998]);
+    }
+
+    #[test]
+    fn should_hand_out_chunk_views_over_slot_ranges() {
+        let mut buf = Buffer::<u16>::padded(10, 8).unwrap();
+        buf.par_chunks_mut(4).enumerate().for_each(|(chunk, mut view)| {
+            for entry in view.iter_mut() {
+                *entry = chunk as u16;
+            }
+        });
+        let lens = buf.par_chunks(4).map(|view| view.cap()).collect::<Vec<_>>();
+        assert_eq!(lens, vec![4, 4, 2]);
+        assert_eq!(buf.to_contiguous(), vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2]);
+    }
+}
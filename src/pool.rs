@@ -0,0 +1,207 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem::ManuallyDrop;
+use core::ops::{ Deref, DerefMut };
+
+use allocator_api2::alloc::Global;
+
+use { new, Backing, Buffer, Error, Padding };
+
+/// Free list of same sized buffers, so hot paths reuse allocations instead of making new ones.
+///
+/// Buffers come back zeroed when their `PooledBuffer` is dropped. At most `max_idle` of them are
+/// kept, any beyond that are released, as are buffers resized, repadded or swapped for one with
+/// another backing while borrowed.
+/// The pool is meant for a single thread, it is not `Sync`.
+pub struct BufferPool<T: 'static> {
+    cap: usize,
+    padding: Padding,
+    max_idle: usize,
+    idle: RefCell<Vec<Buffer<'static, T>>>,
+}
+
+impl <T: 'static> BufferPool<T> {
+    pub fn new(cap: usize, max_idle: usize) -> Self {
+        Self::with_padding(cap, Padding::None, max_idle)
+    }
+
+    pub fn padded(cap: usize, padded_size: usize, max_idle: usize) -> Self {
+        Self::with_padding(cap, Padding::Padded(padded_size), max_idle)
+    }
+
+    pub fn cache_aligned(cap: usize, max_idle: usize) -> Self {
+        Self::with_padding(cap, Padding::CacheAligned, max_idle)
+    }
+
+    pub fn with_padding(cap: usize, padding: Padding, max_idle: usize) -> Self {
+        BufferPool {
+            cap,
+            padding,
+            max_idle,
+            idle: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn max_idle(&self) -> usize {
+        self.max_idle
+    }
+
+    /// Number of buffers waiting to be reused
+    pub fn idle(&self) -> usize {
+        self.idle.borrow().len()
+    }
+
+    /// Hands out an idle buffer, allocating a new one only when none is left
+    pub fn acquire(&self) -> Result<PooledBuffer<'_, T>, Error> {
+        let idle = self.idle.borrow_mut().pop();
+        let buffer = match idle {
+            Some(buffer) => buffer,
+            None => new(self.cap, self.padding, Global)?,
+        };
+        Ok(PooledBuffer {
+            pool: self,
+            buffer: ManuallyDrop::new(buffer),
+        })
+    }
+
+    fn release(&self, mut buffer: Buffer<'static, T>) {
+        // `DerefMut` lets borrowers grow, repad or swap the buffer, only reuse plain heap buffers
+        // still matching what `acquire` would allocate
+        if buffer.cap() != self.cap || buffer.padding_mode() != self.padding || !is_plain(&buffer) {
+            return;
+        }
+        let mut idle = self.idle.borrow_mut();
+        if idle.len() < self.max_idle {
+            buffer.zero();
+            buffer.clear();
+            idle.push(buffer);
+        }
+    }
+}
+
+fn is_plain<T>(buffer: &Buffer<'static, T>) -> bool {
+    #[cfg(feature = "std")]
+    {
+        if buffer.secure || buffer.locked {
+            return false;
+        }
+    }
+    matches!(buffer.backing, Backing::Heap) && buffer.canary == 0 && buffer.initialized.is_none()
+}
+
+/// Buffer borrowed from a `BufferPool`, returned to it on drop
+pub struct PooledBuffer<'p, T: 'static> {
+    pool: &'p BufferPool<T>,
+    buffer: ManuallyDrop<Buffer<'static, T>>,
+}
+
+impl <'p, T: 'static> Deref for PooledBuffer<'p, T> {
+    type Target = Buffer<'static, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl <'p, T: 'static> DerefMut for PooledBuffer<'p, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl <'p, T: 'static> Drop for PooledBuffer<'p, T> {
+    fn drop(&mut self) {
+        let buffer = unsafe {
+            ManuallyDrop::take(&mut self.buffer)
+        };
+        self.pool.release(buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_reuse_released_allocation() {
+        let pool = BufferPool::<u64>::new(4, 2);
+        let first = pool.acquire().unwrap().as_byte_ptr();
+        assert_eq!(pool.idle(), 1);
+        let second = pool.acquire().unwrap();
+        assert_eq!(second.as_byte_ptr(), first);
+        assert_eq!(pool.idle(), 0);
+    }
+
+    #[test]
+    fn should_zero_contents_before_reuse() {
+        let pool = BufferPool::<u64>::cache_aligned(3, 1);
+        {
+            let mut buf = pool.acquire().unwrap();
+            buf.fill(9);
+        }
+        let buf = pool.acquire().unwrap();
+        assert_eq!(buf.padded_size(), ::cache_line_size());
+        assert!(buf.data().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn should_release_buffers_beyond_max_idle() {
+        let pool = BufferPool::<u32>::padded(2, 8, 1);
+        {
+            let a = pool.acquire().unwrap();
+            let b = pool.acquire().unwrap();
+            assert_ne!(a.as_byte_ptr(), b.as_byte_ptr());
+        }
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn should_release_buffers_changed_while_borrowed() {
+        let pool = BufferPool::<u64>::new(4, 2);
+        pool.acquire().unwrap().grow(8).unwrap();
+        assert_eq!(pool.idle(), 0);
+        pool.acquire().unwrap().repad(Padding::CacheAligned).unwrap();
+        assert_eq!(pool.idle(), 0);
+        {
+            let mut buf = pool.acquire().unwrap();
+            let mut other = Buffer::<u64>::padded(4, 16).unwrap();
+            ::core::mem::swap(&mut *buf, &mut other);
+        }
+        assert_eq!(pool.idle(), 0);
+        {
+            let mut buf = pool.acquire().unwrap();
+            let mut other = Buffer::<u64>::tracked(4).unwrap();
+            ::core::mem::swap(&mut *buf, &mut other);
+        }
+        {
+            let mut buf = pool.acquire().unwrap();
+            let mut other = Buffer::<u64>::with_canary(4).unwrap();
+            ::core::mem::swap(&mut *buf, &mut other);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut buf = pool.acquire().unwrap();
+            let mut other = Buffer::<u64>::secure(4).unwrap();
+            ::core::mem::swap(&mut *buf, &mut other);
+        }
+        #[cfg(feature = "mmap")]
+        {
+            let path = std::env::temp_dir().join(format!("omni_buffer_pool_{}", std::process::id()));
+            {
+                let mut buf = pool.acquire().unwrap();
+                let mut other = Buffer::<u64>::mmap_file(&path, 4).unwrap();
+                other[0] = 11;
+                ::core::mem::swap(&mut *buf, &mut other);
+            }
+            assert_eq!(Buffer::<u64>::mmap_file(&path, 4).unwrap()[0], 11);
+            std::fs::remove_file(&path).unwrap();
+        }
+        assert_eq!(pool.idle(), 0);
+        drop(pool.acquire().unwrap());
+        assert_eq!((pool.idle(), pool.acquire().unwrap().cap()), (1, 4));
+    }
+}
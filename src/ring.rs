@@ -0,0 +1,202 @@
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{ AtomicUsize, Ordering };
+
+use { Buffer, Error };
+
+const HEAD: usize = 0;
+const TAIL: usize = 1;
+
+/// Lock-free single-producer single-consumer queue over a power-of-two sized buffer.
+///
+/// `push` and `pop` can be called directly from one thread, or the ring can be `split`
+/// into a `Producer` and a `Consumer` which are each moved to their own thread.
+pub struct SpscRing<T: 'static> {
+    buffer: Buffer<'static, T>,
+    // Head and tail each sit in their own cache line so producer and consumer don't false share
+    counters: Buffer<'static, AtomicUsize>,
+    mask: usize,
+    // Not `Sync`, so `push` and `pop` can never race with themselves on a shared ring
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl <T: 'static> SpscRing<T> {
+    /// Rounds `cap` up to the next power of two so indices wrap with a mask
+    pub fn new(cap: usize) -> Result<Self, Error> {
+        let cap = cap.checked_next_power_of_two()
+            .ok_or(Error::BufferSizeOverflow)?;
+        Ok(SpscRing {
+            buffer: Buffer::new(cap)?,
+            counters: Buffer::cache_aligned(2)?,
+            mask: cap - 1,
+            _not_sync: PhantomData,
+        })
+    }
+
+    pub fn cap(&self) -> usize {
+        self.buffer.cap()
+    }
+
+    pub fn len(&self) -> usize {
+        let tail = self.counters[TAIL].load(Ordering::Acquire);
+        let head = self.counters[HEAD].load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends `value`, handing it back when the ring is full
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.counters[TAIL].load(Ordering::Relaxed);
+        let head = self.counters[HEAD].load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == self.cap() {
+            return Err(value);
+        }
+        unsafe {
+            ptr::write(self.buffer.entry_ptr(tail & self.mask) as *mut T, value)
+        }
+        self.counters[TAIL].store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let head = self.counters[HEAD].load(Ordering::Relaxed);
+        let tail = self.counters[TAIL].load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let value = unsafe {
+            ptr::read(self.buffer.entry_ptr(head & self.mask))
+        };
+        self.counters[HEAD].store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Splits the ring into halves that can be moved to the producing and consuming threads
+    pub fn split(&mut self) -> (Producer<'_, T>, Consumer<'_, T>) {
+        let ring: &SpscRing<T> = self;
+        (
+            Producer { ring, _marker: PhantomData },
+            Consumer { ring, _marker: PhantomData },
+        )
+    }
+}
+
+impl <T: 'static> Drop for SpscRing<T> {
+    fn drop(&mut self) {
+        // The buffer never drops its entries, so release whatever is still queued
+        while self.pop().is_some() {}
+    }
+}
+
+/// Pushing half of a split `SpscRing`
+pub struct Producer<'r, T: 'static> {
+    ring: &'r SpscRing<T>,
+    // Not `Sync`, only one thread at a time may push
+    _marker: PhantomData<*const ()>,
+}
+
+unsafe impl<'r, T: Send> Send for Producer<'r, T> {}
+
+impl <'r, T: 'static> Producer<'r, T> {
+    pub fn push(&self, value: T) -> Result<(), T> {
+        self.ring.push(value)
+    }
+}
+
+/// Popping half of a split `SpscRing`
+pub struct Consumer<'r, T: 'static> {
+    ring: &'r SpscRing<T>,
+    // Not `Sync`, only one thread at a time may pop
+    _marker: PhantomData<*const ()>,
+}
+
+unsafe impl<'r, T: Send> Send for Consumer<'r, T> {}
+
+impl <'r, T: 'static> Consumer<'r, T> {
+    pub fn pop(&self) -> Option<T> {
+        self.ring.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn should_round_capacity_up_to_power_of_two() {
+        let ring = SpscRing::<u32>::new(5).unwrap();
+        assert_eq!(ring.cap(), 8);
+    }
+
+    #[test]
+    fn should_place_head_and_tail_on_separate_cache_lines() {
+        let ring = SpscRing::<u32>::new(4).unwrap();
+        let head = &ring.counters[HEAD] as *const AtomicUsize as usize;
+        let tail = &ring.counters[TAIL] as *const AtomicUsize as usize;
+        assert!(tail - head >= ::cache_line_size());
+    }
+
+    #[test]
+    fn should_push_until_full_then_pop_in_order() {
+        let ring = SpscRing::<u32>::new(2).unwrap();
+        assert!(ring.is_empty());
+        assert_eq!(ring.push(1), Ok(()));
+        assert_eq!(ring.push(2), Ok(()));
+        assert_eq!(ring.push(3), Err(3));
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.push(3), Ok(()));
+        assert_eq!(ring.pop(), Some(2));
+        assert_eq!(ring.pop(), Some(3));
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn should_move_items_between_threads_without_loss_or_reordering() {
+        const N: usize = 100_000;
+        let mut ring = SpscRing::<usize>::new(64).unwrap();
+        let (producer, consumer) = ring.split();
+        let received = thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..N {
+                    let mut value = i;
+                    while let Err(rejected) = producer.push(value) {
+                        value = rejected;
+                        thread::yield_now();
+                    }
+                }
+            });
+            let consumer = scope.spawn(move || {
+                let mut received = Vec::with_capacity(N);
+                while received.len() < N {
+                    match consumer.pop() {
+                        Some(value) => received.push(value),
+                        None => thread::yield_now(),
+                    }
+                }
+                received
+            });
+            consumer.join().unwrap()
+        });
+        assert_eq!(received, (0..N).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn should_drop_values_still_queued() {
+        use std::rc::Rc;
+        let value = Rc::new(());
+        {
+            let ring = SpscRing::<Rc<()>>::new(4).unwrap();
+            ring.push(value.clone()).unwrap();
+            ring.push(value.clone()).unwrap();
+            assert_eq!(Rc::strong_count(&value), 3);
+        }
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
@@ -0,0 +1,154 @@
+use core::cell::UnsafeCell;
+use core::hint;
+use core::ptr;
+use core::sync::atomic::{ self, AtomicUsize, Ordering };
+
+use { view, Buffer, BufferViewMut, Error };
+
+/// Buffer guarded by a sequence counter, so readers take consistent snapshots without blocking the writer.
+///
+/// The counter is odd while a write is in progress. `read_into` copies the bytes and reports whether
+/// the counter moved meanwhile, in which case the copy may be torn and the caller retries. Writes are
+/// handed a view rather than the buffer itself, so the allocation can never move under a reader.
+pub struct SeqlockBuffer<T: Copy + 'static> {
+    buffer: UnsafeCell<Buffer<'static, T>>,
+    sequence: AtomicUsize,
+}
+
+unsafe impl<T: Copy + Send + 'static> Send for SeqlockBuffer<T> {}
+unsafe impl<T: Copy + Send + 'static> Sync for SeqlockBuffer<T> {}
+
+impl <T: Copy + 'static> SeqlockBuffer<T> {
+    pub fn new(cap: usize) -> Result<Self, Error> {
+        Ok(Self::from_buffer(Buffer::new(cap)?))
+    }
+
+    pub fn from_buffer(buffer: Buffer<'static, T>) -> Self {
+        SeqlockBuffer {
+            buffer: UnsafeCell::new(buffer),
+            sequence: AtomicUsize::new(0),
+        }
+    }
+
+    fn buffer(&self) -> &Buffer<'static, T> {
+        unsafe {
+            &*self.buffer.get()
+        }
+    }
+
+    pub fn cap(&self) -> usize {
+        self.buffer().cap()
+    }
+
+    pub fn padded_size(&self) -> usize {
+        self.buffer().padded_size()
+    }
+
+    /// Current value of the counter, even unless a write is in progress
+    pub fn sequence(&self) -> usize {
+        self.sequence.load(Ordering::Acquire)
+    }
+
+    /// Applies `f` to the entries between two bumps of the counter. Concurrent writers take turns.
+    pub fn write<F: FnOnce(&mut BufferViewMut<'_, T>)>(&self, f: F) {
+        let mut sequence = self.sequence.load(Ordering::Relaxed);
+        loop {
+            if sequence.is_multiple_of(2) {
+                match self.sequence.compare_exchange_weak(sequence, sequence + 1, Ordering::Acquire, Ordering::Relaxed) {
+                    Ok(_) => break,
+                    Err(current) => sequence = current,
+                }
+            } else {
+                hint::spin_loop();
+                sequence = self.sequence.load(Ordering::Relaxed);
+            }
+        }
+        // Keeps the writes below from being reordered before the counter turns odd
+        atomic::fence(Ordering::Release);
+        let buffer = self.buffer();
+        let mut entries = unsafe {
+            view::view_mut(buffer.ptr, buffer.cap, buffer.size, buffer.padded_size)
+        };
+        f(&mut entries);
+        self.sequence.store(sequence + 2, Ordering::Release);
+    }
+
+    /// Copies every byte into `dst`, returning `false` when a write overlapped the copy.
+    /// Panics unless `dst` has the same `cap` and `padded_size`.
+    pub fn read_into(&self, dst: &mut Buffer<'_, T>) -> bool {
+        let buffer = self.buffer();
+        assert!(dst.cap() == buffer.cap() && dst.padded_size() == buffer.padded_size(),
+            "seqlock snapshots need a buffer of the same layout");
+        let before = self.sequence.load(Ordering::Acquire);
+        if !before.is_multiple_of(2) {
+            return false;
+        }
+        // Volatile reads since the writer may be changing the bytes underneath, the counter tells
+        // whether the copy can be trusted
+        let out = dst.data_mut();
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = unsafe {
+                ptr::read_volatile(buffer.ptr.add(i))
+            };
+        }
+        atomic::fence(Ordering::Acquire);
+        self.sequence.load(Ordering::Relaxed) == before
+    }
+
+    pub fn into_inner(self) -> Buffer<'static, T> {
+        self.buffer.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn should_bump_sequence_twice_per_write() {
+        let lock = SeqlockBuffer::<u32>::new(4).unwrap();
+        lock.write(|entries| entries[2] = 7);
+        assert_eq!(lock.sequence(), 2);
+        let mut snapshot = Buffer::<u32>::new(4).unwrap();
+        assert!(lock.read_into(&mut snapshot));
+        assert_eq!(snapshot.to_contiguous(), vec![0, 0, 7, 0]);
+        assert_eq!(lock.into_inner()[2], 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "same layout")]
+    fn should_refuse_snapshot_into_other_layout() {
+        let lock = SeqlockBuffer::<u32>::new(4).unwrap();
+        lock.read_into(&mut Buffer::<u32>::padded(4, 8).unwrap());
+    }
+
+    #[test]
+    fn should_never_observe_torn_updates() {
+        const WRITES: u64 = 20_000;
+        let lock = SeqlockBuffer::<u64>::new(64).unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for round in 1..=WRITES {
+                    lock.write(|entries| {
+                        for entry in entries.iter_mut() {
+                            *entry = round;
+                        }
+                    });
+                }
+            });
+            let mut snapshot = Buffer::<u64>::new(64).unwrap();
+            let mut last = 0;
+            while last < WRITES {
+                if lock.read_into(&mut snapshot) {
+                    let first = snapshot[0];
+                    assert!(snapshot.iter().all(|entry| *entry == first), "torn snapshot: {:?}", snapshot);
+                    assert!(first >= last);
+                    last = first;
+                } else {
+                    thread::yield_now();
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+use core::ptr;
+
+use allocator_api2::alloc::Allocator;
+use serde::de::Error as DeError;
+use serde::ser::SerializeStruct;
+use serde::{ Deserialize, Deserializer, Serialize, Serializer };
+
+use { Buffer, Padding };
+
+// Entries only, padding bytes are never written out
+struct Entries<'b, 'a: 'b, T: 'a, A: Allocator + 'b>(&'b Buffer<'a, T, A>);
+
+impl <'b, 'a: 'b, T: Serialize + 'a, A: Allocator + 'b> Serialize for Entries<'b, 'a, T, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+// Mirrors `Padding`, which stays free of serde derives
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Padding")]
+enum Mode {
+    None,
+    Padded(usize),
+    CacheAligned,
+    Pow2,
+    Isolated,
+}
+
+impl From<Padding> for Mode {
+    fn from(padding: Padding) -> Self {
+        match padding {
+            Padding::None => Mode::None,
+            Padding::Padded(padded_size) => Mode::Padded(padded_size),
+            Padding::CacheAligned => Mode::CacheAligned,
+            Padding::Pow2 => Mode::Pow2,
+            Padding::Isolated => Mode::Isolated,
+        }
+    }
+}
+
+impl From<Mode> for Padding {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::None => Padding::None,
+            Mode::Padded(padded_size) => Padding::Padded(padded_size),
+            Mode::CacheAligned => Padding::CacheAligned,
+            Mode::Pow2 => Padding::Pow2,
+            Mode::Isolated => Padding::Isolated,
+        }
+    }
+}
+
+/// Serialized as `{ padded_size, padding, align, entries }` so a padded buffer comes back with the
+/// same stride, padding mode and base alignment
+impl <'a, T: Serialize + 'a, A: Allocator> Serialize for Buffer<'a, T, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Buffer", 4)?;
+        state.serialize_field("padded_size", &self.padded_size())?;
+        state.serialize_field("padding", &Mode::from(self.padding_mode()))?;
+        state.serialize_field("align", &self.alignment())?;
+        state.serialize_field("entries", &Entries(self))?;
+        state.end()
+    }
+}
+
+// `padding` and `align` are missing from snapshots written before they were recorded, those come
+// back as `Padded(padded_size)` with the entry type's alignment
+#[derive(Deserialize)]
+#[serde(rename = "Buffer")]
+struct Snapshot<T> {
+    padded_size: usize,
+    #[serde(default)]
+    padding: Option<Mode>,
+    #[serde(default)]
+    align: Option<usize>,
+    entries: Vec<T>,
+}
+
+impl <'de, 'a, T: Deserialize<'de> + 'a> Deserialize<'de> for Buffer<'a, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let snapshot = Snapshot::<T>::deserialize(deserializer)?;
+        let padding = snapshot.padding.map_or(Padding::Padded(snapshot.padded_size), Padding::from);
+        let mut builder = Buffer::builder()
+            .capacity(snapshot.entries.len())
+            .padding(padding);
+        if let Some(align) = snapshot.align {
+            builder = builder.align(align);
+        }
+        let mut buffer = builder.build().map_err(D::Error::custom)?;
+        if buffer.padded_size() != snapshot.padded_size {
+            return Err(D::Error::custom("padded_size does not match the padding of the entry type"));
+        }
+        for (index, value) in snapshot.entries.into_iter().enumerate() {
+            unsafe {
+                ptr::write(buffer.entry_ptr_mut(index), value)
+            }
+        }
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn should_round_trip_unpadded_buffer() {
+        let mut buf = Buffer::<u32>::new(3).unwrap();
+        buf.copy_from_slice(&[1, 2, 3]).unwrap();
+        let json = serde_json::to_string(&buf).unwrap();
+        assert_eq!(json, r#"{"padded_size":4,"padding":"None","align":4,"entries":[1,2,3]}"#);
+        let restored: Buffer<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.padded_size(), 4);
+        assert_eq!(restored.iter().collect::<Vec<_>>(), buf.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn should_round_trip_padded_buffer_layout() {
+        let mut buf = Buffer::<u64>::cache_aligned(2).unwrap();
+        buf[0] = 7;
+        buf[1] = 9;
+        let json = serde_json::to_string(&buf).unwrap();
+        let restored: Buffer<u64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.padded_size(), ::cache_line_size());
+        assert_eq!(restored.padding_mode(), Padding::CacheAligned);
+        assert_eq!(restored.as_byte_ptr() as usize % ::cache_line_size(), 0);
+        assert_eq!(restored.data(), buf.data());
+        let isolated = Buffer::<[u8; 3]>::with_padding(2, Padding::Isolated).unwrap();
+        let restored: Buffer<[u8; 3]> = serde_json::from_str(&serde_json::to_string(&isolated).unwrap()).unwrap();
+        assert_eq!((restored.padding_mode(), restored.alignment()), (Padding::Isolated, isolated.alignment()));
+    }
+
+    #[test]
+    fn should_round_trip_base_alignment_of_aligned_buffer() {
+        let buf = Buffer::<u32>::aligned(2, 32).unwrap();
+        let restored: Buffer<u32> = serde_json::from_str(&serde_json::to_string(&buf).unwrap()).unwrap();
+        assert_eq!((restored.padded_size(), restored.alignment()), (buf.padded_size(), 32));
+        assert_eq!(restored.as_byte_ptr() as usize % 32, 0);
+    }
+
+    #[test]
+    fn should_read_snapshots_without_padding_mode() {
+        let restored: Buffer<u32> = serde_json::from_str(r#"{"padded_size":8,"entries":[1,2]}"#).unwrap();
+        assert_eq!((restored.padding_mode(), restored[1]), (Padding::Padded(8), 2));
+    }
+
+    #[test]
+    fn should_reject_stride_smaller_than_entry() {
+        let json = r#"{"padded_size":2,"entries":[1]}"#;
+        assert!(serde_json::from_str::<Buffer<u32>>(json).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use core::marker::PhantomData;
+use core::ops::{ Index, IndexMut };
+
+use { slot_offset, Iter, IterMut };
+
+/// Read-only view over a range of a buffer's slots, sharing the parent's allocation and stride.
+/// The view never owns the allocation, so nothing is released when it is dropped.
+pub struct BufferView<'b, T: 'b> {
+    ptr: *const u8,
+    cap: usize,
+    size: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b T>,
+}
+
+unsafe impl<'b, T: Sync + 'b> Send for BufferView<'b, T> {}
+unsafe impl<'b, T: Sync + 'b> Sync for BufferView<'b, T> {}
+
+// Callers guarantee `ptr` addresses `cap` slots that stay borrowed for `'b`
+pub(crate) unsafe fn view<'b, T>(ptr: *const u8, cap: usize, size: usize, padded_size: usize) -> BufferView<'b, T> {
+    BufferView {
+        ptr,
+        cap,
+        size,
+        padded_size,
+        _marker: PhantomData,
+    }
+}
+
+impl <'b, T: 'b> BufferView<'b, T> {
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn padded_size(&self) -> usize {
+        self.padded_size
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'b T> {
+        if index >= self.cap {
+            return None;
+        }
+        Some(unsafe {
+            &*(self.ptr.add(slot_offset(index, self.padded_size)) as *const T)
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'b, T> {
+        Iter {
+            ptr: self.ptr as *mut u8,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl <'b, T: 'b> Clone for BufferView<'b, T> {
+    fn clone(&self) -> Self {
+        unsafe {
+            view(self.ptr, self.cap, self.size, self.padded_size)
+        }
+    }
+}
+
+impl <'b, T: 'b> Index<usize> for BufferView<'b, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let cap = self.cap;
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", cap, index))
+    }
+}
+
+/// Mutable view over a range of a buffer's slots, sharing the parent's allocation and stride.
+/// The view never owns the allocation, so nothing is released when it is dropped.
+pub struct BufferViewMut<'b, T: 'b> {
+    ptr: *mut u8,
+    cap: usize,
+    size: usize,
+    padded_size: usize,
+    _marker: PhantomData<&'b mut T>,
+}
+
+unsafe impl<'b, T: Send + 'b> Send for BufferViewMut<'b, T> {}
+unsafe impl<'b, T: Sync + 'b> Sync for BufferViewMut<'b, T> {}
+
+// Callers guarantee `ptr` addresses `cap` slots that stay exclusively borrowed for `'b`
+pub(crate) unsafe fn view_mut<'b, T>(ptr: *mut u8, cap: usize, size: usize, padded_size: usize) -> BufferViewMut<'b, T> {
+    BufferViewMut {
+        ptr,
+        cap,
+        size,
+        padded_size,
+        _marker: PhantomData,
+    }
+}
+
+impl <'b, T: 'b> BufferViewMut<'b, T> {
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn padded_size(&self) -> usize {
+        self.padded_size
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.cap {
+            return None;
+        }
+        Some(unsafe {
+            &*(self.ptr.add(slot_offset(index, self.padded_size)) as *const T)
+        })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.cap {
+            return None;
+        }
+        Some(unsafe {
+            &mut *(self.ptr.add(slot_offset(index, self.padded_size)) as *mut T)
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            ptr: self.ptr,
+            index: 0,
+            cap: self.cap,
+            padded_size: self.padded_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl <'b, T: 'b> Index<usize> for BufferViewMut<'b, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let cap = self.cap;
+        self.get(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", cap, index))
+    }
+}
+
+impl <'b, T: 'b> IndexMut<usize> for BufferViewMut<'b, T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let cap = self.cap;
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index out of bounds: the cap is {} but the index is {}", cap, index))
+    }
+}
@@ -0,0 +1,57 @@
+extern crate omni_buffer;
+
+use std::alloc::{ GlobalAlloc, Layout, System };
+use std::cell::Cell;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use omni_buffer::Buffer;
+
+// Counts the allocations made by the thread that opted in, the harness allocates on threads of its own
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTED: Cell<bool> = const { Cell::new(false) };
+}
+
+fn count() {
+    if COUNTED.try_with(Cell::get).unwrap_or(false) {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::SeqCst)
+}
+
+#[test]
+fn should_allocate_only_the_data_region_until_entries_are_requested() {
+    COUNTED.with(|counted| counted.set(true));
+    let before = allocations();
+    let mut buf = Buffer::<u64>::new(1_000_000).unwrap();
+    buf[999_999] = 7;
+    assert_eq!(buf.iter().sum::<u64>(), 7);
+    assert_eq!(allocations() - before, 1);
+    assert_eq!(*buf.entries()[999_999], 7);
+    assert_eq!(allocations() - before, 2);
+}
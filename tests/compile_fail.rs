@@ -0,0 +1,8 @@
+extern crate trybuild;
+
+// Each case must be rejected by the borrow checker, the expected errors sit next to them as `.stderr`
+#[test]
+fn should_reject_entry_references_outliving_the_buffer() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/compile_fail/*.rs");
+}
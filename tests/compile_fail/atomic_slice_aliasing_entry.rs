@@ -0,0 +1,12 @@
+extern crate omni_buffer;
+
+use std::sync::atomic::Ordering;
+
+use omni_buffer::Buffer;
+
+fn main() {
+    let mut buf = Buffer::<u64>::new(2).unwrap();
+    let entry = &buf[0];
+    buf.as_atomic_slice().unwrap()[0].store(1, Ordering::Relaxed);
+    assert_eq!(*entry, 1);
+}
@@ -0,0 +1,10 @@
+extern crate omni_buffer;
+
+use omni_buffer::Buffer;
+
+fn main() {
+    let buf = Buffer::<u32>::new(2).unwrap();
+    let entries = buf.entries();
+    drop(buf);
+    assert_eq!(*entries[0], 0);
+}
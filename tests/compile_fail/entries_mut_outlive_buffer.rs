@@ -0,0 +1,12 @@
+extern crate omni_buffer;
+
+use omni_buffer::Buffer;
+
+fn main() {
+    let entry;
+    {
+        let mut buf = Buffer::<u32>::new(2).unwrap();
+        entry = buf.entries_mut().remove(0);
+    }
+    *entry = 1;
+}
@@ -0,0 +1,10 @@
+extern crate omni_buffer;
+
+use omni_buffer::Buffer;
+
+fn main() {
+    let mut buf = Buffer::<u32>::new(2).unwrap();
+    let entry = buf.get_mut(1).unwrap();
+    drop(buf);
+    *entry = 1;
+}
@@ -0,0 +1,13 @@
+//! Compile check that the buffer is usable from a `no_std` crate.
+//! Build on its own with `cargo build -p omni_buffer_no_std` to keep `std` out of the feature set.
+#![no_std]
+
+extern crate omni_buffer;
+
+use omni_buffer::{ Buffer, Error };
+
+pub fn build() -> Result<Buffer<'static, u32>, Error> {
+    let mut buffer = Buffer::new(4)?;
+    buffer[0] = 1;
+    Ok(buffer)
+}